@@ -1,5 +1,6 @@
 use async_graphql::Result;
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 use std::env;
 
@@ -71,7 +72,7 @@ pub struct SpartanTokenResponse {
 #[serde(rename_all = "PascalCase")]
 pub struct SpartanTokenExpiresUtc {
     #[serde(rename = "ISO8601Date")]
-    pub iso8601_date: String,
+    pub iso8601_date: DateTime<Utc>,
 }
 
 pub async fn spartan_token(client: &Client, xsts_token: String) -> Result<SpartanTokenResponse> {
@@ -136,7 +137,19 @@ pub async fn auth_token(
     code: Option<String>,
     refresh_token: Option<String>,
 ) -> Result<AuthTokenResponse> {
-    Ok(client
+    Ok(auth_token_response(client, code, refresh_token)
+        .await?
+        .error_for_status()?
+        .json::<AuthTokenResponse>()
+        .await?)
+}
+
+async fn auth_token_response(
+    client: &Client,
+    code: Option<String>,
+    refresh_token: Option<String>,
+) -> reqwest::Result<Response> {
+    client
         .post(env::var("AUTH_TOKEN_URL").expect("Missing Auth Token Url"))
         .form(&[
             (
@@ -173,9 +186,38 @@ pub async fn auth_token(
             ),
         ])
         .send()
-        .await?
-        .json::<AuthTokenResponse>()
-        .await?)
+        .await
+}
+
+/// Spartan token along with the refresh token to sign in with next time
+pub struct SignIn {
+    pub spartan_token: SpartanTokenResponse,
+    pub refresh_token: String,
+}
+
+/// Signs in to Halo services with OAuth tokens.
+pub async fn sign_in(client: &Client, auth_token: AuthTokenResponse) -> Result<SignIn> {
+    let user_token = user_token(client, auth_token.access_token).await?;
+    let xsts_token = xsts_token(client, user_token.token).await?.token;
+
+    Ok(SignIn {
+        spartan_token: spartan_token(client, xsts_token).await?,
+        refresh_token: auth_token.refresh_token,
+    })
+}
+
+/// Signs in again with a refresh token, or `None` if it was turned down and
+/// the user has to sign in from scratch.
+pub async fn refresh(client: &Client, refresh_token: &str) -> Result<Option<SignIn>> {
+    let res = auth_token_response(client, None, Some(refresh_token.to_string())).await?;
+
+    if res.status().is_client_error() {
+        return Ok(None);
+    }
+
+    let auth_token = res.error_for_status()?.json::<AuthTokenResponse>().await?;
+
+    sign_in(client, auth_token).await.map(Some)
 }
 
 pub fn redirect_url() -> String {
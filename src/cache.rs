@@ -0,0 +1,477 @@
+use async_graphql::Result;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Size-bounded cache shared across requests for upstream data that never
+/// changes once published, such as the stats of a finished match or a
+/// specific version of a UGC asset, evicting the least recently used entry.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    state: Mutex<LruState<K, V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct LruState<K, V> {
+    /// Values along with the tick they were last used at
+    entries: HashMap<K, (V, u64)>,
+    /// Keys ordered by the tick they were last used at
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            state: Mutex::new(LruState {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                tick: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        let Some((value, used)) = state.entries.get_mut(key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+
+        let value = value.clone();
+        let previous = std::mem::replace(used, tick);
+        state.order.remove(&previous);
+        state.order.insert(tick, key.clone());
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        Some(value)
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        if let Some((_, previous)) = state.entries.insert(key.clone(), (value, tick)) {
+            state.order.remove(&previous);
+        }
+        state.order.insert(tick, key);
+
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.order.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+/// Cache shared across requests and tokens for upstream data that changes,
+/// such as profiles. Entries past their TTL are still served for a while
+/// as a background fetch replaces them, and concurrent misses on a key share
+/// one fetch. When full, the entry fetched longest ago makes room.
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    /// How long past the TTL an entry may be served while revalidating
+    stale: Duration,
+    capacity: usize,
+    state: Mutex<TtlState<K, V>>,
+    /// Fetches under way for missing keys, awaited by every caller missing
+    /// the same key
+    in_flight: Mutex<HashMap<K, Shared<BoxFuture<'static, Result<V>>>>>,
+}
+
+struct TtlState<K, V> {
+    entries: HashMap<K, TtlEntry<V>>,
+    /// Keys ordered by the tick they were fetched at
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+struct TtlEntry<V> {
+    value: V,
+    fetched_at: Instant,
+    /// Position in `TtlState::order`
+    tick: u64,
+    refreshing: bool,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new(ttl: Duration, stale: Duration, capacity: usize) -> Self {
+        TtlCache {
+            ttl,
+            stale,
+            capacity,
+            state: Mutex::new(TtlState {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                tick: 0,
+            }),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached value for `key`, calling `fetch` when there is none
+    /// or it is too old to serve.
+    pub async fn get_or_fetch<F, Fut>(self: &Arc<Self>, key: K, fetch: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+    {
+        let stale = {
+            let mut state = self.state.lock().unwrap();
+
+            match state.entries.get_mut(&key) {
+                Some(entry) if entry.fetched_at.elapsed() < self.ttl => {
+                    return Ok(entry.value.clone());
+                }
+                Some(entry) if entry.fetched_at.elapsed() < self.ttl + self.stale => {
+                    // Only the first request past the TTL revalidates
+                    let refresh = !entry.refreshing;
+                    entry.refreshing = true;
+
+                    Some((entry.value.clone(), refresh))
+                }
+                _ => None,
+            }
+        };
+
+        match stale {
+            Some((value, refresh)) => {
+                if refresh {
+                    let cache = self.clone();
+
+                    actix_web::rt::spawn(async move {
+                        match fetch().await {
+                            Ok(value) => cache.insert(key, value),
+                            Err(_) => cache.refresh_failed(&key),
+                        }
+                    });
+                }
+
+                Ok(value)
+            }
+            None => self.fetch_shared(key, fetch).await,
+        }
+    }
+
+    /// Joins the fetch under way for `key`, or starts one.
+    async fn fetch_shared<F, Fut>(self: &Arc<Self>, key: K, fetch: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<V>> + Send + 'static,
+    {
+        let shared = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+
+            match in_flight.get(&key) {
+                Some(shared) => shared.clone(),
+                None => {
+                    // Weak, as the future is kept in the cache until it is done
+                    let cache = Arc::downgrade(self);
+                    let owned_key = key.clone();
+
+                    let shared = async move {
+                        let res = fetch().await;
+
+                        if let Some(cache) = cache.upgrade() {
+                            if let Ok(value) = &res {
+                                cache.insert(owned_key.clone(), value.clone());
+                            }
+
+                            cache.in_flight.lock().unwrap().remove(&owned_key);
+                        }
+
+                        res
+                    }
+                    .boxed()
+                    .shared();
+
+                    in_flight.insert(key, shared.clone());
+                    shared
+                }
+            }
+        };
+
+        shared.await
+    }
+
+    fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        let entry = TtlEntry {
+            value,
+            fetched_at: Instant::now(),
+            tick,
+            refreshing: false,
+        };
+
+        if let Some(previous) = state.entries.insert(key.clone(), entry) {
+            state.order.remove(&previous.tick);
+        }
+        state.order.insert(tick, key);
+
+        // Expired entries are the oldest, so they go first
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.order.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+    }
+
+    fn refresh_failed(&self, key: &K) {
+        if let Some(entry) = self.state.lock().unwrap().entries.get_mut(key) {
+            entry.refreshing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_least_recently_used_entry() {
+        let cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+
+        // Using 1 makes 2 the oldest
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some("c"));
+    }
+
+    #[test]
+    fn replaces_entries_in_place() {
+        let cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(1, "c");
+        cache.insert(3, "d");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&1), Some("c"));
+        assert_eq!(cache.get(&2), None);
+    }
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let cache = LruCache::new(2);
+        cache.insert(1, "a");
+
+        cache.get(&1);
+        cache.get(&1);
+        cache.get(&2);
+
+        assert_eq!(cache.hits(), 2);
+        assert_eq!(cache.misses(), 1);
+    }
+
+    #[test]
+    fn stores_nothing_without_capacity() {
+        let cache = LruCache::new(0);
+        cache.insert(1, "a");
+
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.get(&1), None);
+    }
+
+    /// Cache with the given TTL and staleness window in milliseconds.
+    fn ttl_cache(ttl: u64, stale: u64, capacity: usize) -> Arc<TtlCache<u32, u32>> {
+        Arc::new(TtlCache::new(
+            Duration::from_millis(ttl),
+            Duration::from_millis(stale),
+            capacity,
+        ))
+    }
+
+    /// Fetch returning `value`, counting its calls in `calls`.
+    fn counted(
+        calls: &Arc<AtomicU64>,
+        value: u32,
+    ) -> impl FnOnce() -> futures::future::Ready<Result<u32>> + Send + 'static {
+        let calls = calls.clone();
+
+        move || {
+            calls.fetch_add(1, Ordering::Relaxed);
+            futures::future::ready(Ok(value))
+        }
+    }
+
+    async fn sleep_ms(ms: u64) {
+        actix_web::rt::time::sleep(Duration::from_millis(ms)).await;
+    }
+
+    #[actix_web::test]
+    async fn serves_fresh_entries_without_fetching() {
+        let cache = ttl_cache(10_000, 0, 10);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 1)).await, Ok(1));
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 2)).await, Ok(1));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[actix_web::test]
+    async fn serves_stale_entries_while_revalidating_once() {
+        let cache = ttl_cache(20, 10_000, 10);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        cache.get_or_fetch(1, counted(&calls, 1)).await.unwrap();
+        sleep_ms(40).await;
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 2)).await, Ok(1));
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 3)).await, Ok(1));
+
+        // Let the background fetch land
+        sleep_ms(10).await;
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 4)).await, Ok(2));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[actix_web::test]
+    async fn retries_failed_revalidations() {
+        let cache = ttl_cache(20, 10_000, 10);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        cache.get_or_fetch(1, counted(&calls, 1)).await.unwrap();
+        sleep_ms(40).await;
+
+        let failing = || futures::future::ready(Err(async_graphql::Error::new("down")));
+        assert_eq!(cache.get_or_fetch(1, failing).await, Ok(1));
+        sleep_ms(10).await;
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 2)).await, Ok(1));
+        sleep_ms(10).await;
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 3)).await, Ok(2));
+    }
+
+    #[actix_web::test]
+    async fn refetches_entries_too_old_to_serve() {
+        let cache = ttl_cache(10, 10, 10);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        cache.get_or_fetch(1, counted(&calls, 1)).await.unwrap();
+        sleep_ms(40).await;
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 2)).await, Ok(2));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[actix_web::test]
+    async fn does_not_cache_errors() {
+        let cache = ttl_cache(10_000, 0, 10);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        let failing = || futures::future::ready(Err(async_graphql::Error::new("down")));
+        assert!(cache.get_or_fetch(1, failing).await.is_err());
+
+        assert_eq!(cache.get_or_fetch(1, counted(&calls, 1)).await, Ok(1));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[actix_web::test]
+    async fn evicts_the_oldest_entry_when_full() {
+        let cache = ttl_cache(10_000, 0, 2);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        cache.get_or_fetch(1, counted(&calls, 1)).await.unwrap();
+        cache.get_or_fetch(2, counted(&calls, 2)).await.unwrap();
+        cache.get_or_fetch(3, counted(&calls, 3)).await.unwrap();
+
+        let entries = &cache.state.lock().unwrap().entries;
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.contains_key(&1));
+        assert!(entries.contains_key(&3));
+    }
+
+    #[actix_web::test]
+    async fn shares_one_fetch_between_concurrent_misses() {
+        let cache = ttl_cache(10_000, 0, 10);
+        let calls = Arc::new(AtomicU64::new(0));
+
+        let slow = |value| {
+            let calls = calls.clone();
+
+            move || async move {
+                calls.fetch_add(1, Ordering::Relaxed);
+                sleep_ms(20).await;
+                Ok(value)
+            }
+        };
+
+        let results = futures::future::join_all([
+            cache.get_or_fetch(1, slow(1)),
+            cache.get_or_fetch(1, slow(2)),
+            cache.get_or_fetch(1, slow(3)),
+        ])
+        .await;
+
+        assert!(results.iter().all(|x| *x == Ok(1)));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn shares_failed_fetches_without_keeping_them() {
+        let cache = ttl_cache(10_000, 0, 10);
+
+        let results = futures::future::join(
+            cache.get_or_fetch(1, || async {
+                sleep_ms(20).await;
+                Err(async_graphql::Error::new("down"))
+            }),
+            cache.get_or_fetch(1, || async { Ok(2) }),
+        )
+        .await;
+
+        assert!(results.0.is_err() && results.1.is_err());
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+        assert!(cache.state.lock().unwrap().entries.is_empty());
+    }
+}
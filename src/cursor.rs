@@ -0,0 +1,231 @@
+use crate::enums::MatchType;
+use crate::halo_requests;
+use crate::AuthData;
+use async_graphql::connection::CursorType;
+use async_graphql::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+
+/// How far past its recorded offset a cursor's anchor match is searched for
+/// before the cursor is considered stale.
+const MAX_DRIFT: usize = 100;
+
+/// Largest offset a cursor may carry. Far beyond any real history, and keeps
+/// offset arithmetic on client supplied cursors clear of overflow.
+const MAX_OFFSET: usize = 1 << 24;
+
+/// Opaque match history cursor anchored to a specific match, so it stays
+/// valid while new matches are prepended to the history.
+pub struct MatchCursor {
+    pub offset: usize,
+    pub match_id: String,
+    pub start_time: DateTime<Utc>,
+}
+
+/// Where a cursor's anchor match currently sits in the history.
+pub struct ResolvedCursor {
+    pub offset: usize,
+    /// `false` when the anchor is no longer in the history, in which case
+    /// `offset` is the position of the first match older than it.
+    pub found: bool,
+}
+
+impl ResolvedCursor {
+    /// First offset after the anchor.
+    pub fn after(&self) -> usize {
+        if self.found {
+            self.offset + 1
+        } else {
+            self.offset
+        }
+    }
+
+    /// First offset not before the anchor.
+    pub fn before(&self) -> usize {
+        self.offset
+    }
+}
+
+impl MatchCursor {
+    pub async fn resolve(
+        &self,
+        data: &AuthData,
+        xuid: &str,
+        match_type: Option<MatchType>,
+    ) -> Result<ResolvedCursor> {
+        let mut start = self.offset;
+
+        while start <= self.offset + MAX_DRIFT {
+            let res = halo_requests::matches(
+                &data.client,
+                &data.spartan_token,
+                xuid,
+                match_type,
+                Some(start),
+                Some(halo_requests::MATCHES_PAGE_SIZE),
+            )
+            .await?;
+
+            if let Some(resolved) = self.locate(start, &res.results) {
+                return Ok(resolved);
+            }
+
+            start += halo_requests::MATCHES_PAGE_SIZE;
+        }
+
+        Err(async_graphql::Error::new("Cursor is too stale"))
+    }
+
+    /// Looks for the anchor in the page of history starting at `start`, or
+    /// `None` when it may be on a later page.
+    fn locate(&self, start: usize, page: &[halo_requests::Match]) -> Option<ResolvedCursor> {
+        if let Some(ind) = page
+            .iter()
+            .position(|x| x.match_id == self.match_id || x.match_info.start_time < self.start_time)
+        {
+            return Some(ResolvedCursor {
+                offset: start + ind,
+                found: page[ind].match_id == self.match_id,
+            });
+        }
+
+        (page.len() < halo_requests::MATCHES_PAGE_SIZE).then_some(ResolvedCursor {
+            offset: start + page.len(),
+            found: false,
+        })
+    }
+}
+
+impl CursorType for MatchCursor {
+    type Error = String;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let invalid = || "Invalid cursor".to_string();
+
+        let decoded = URL_SAFE_NO_PAD.decode(s).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let mut parts = decoded.splitn(3, ':');
+
+        let offset = parts
+            .next()
+            .and_then(|x| x.parse().ok())
+            .filter(|x| *x <= MAX_OFFSET)
+            .ok_or_else(invalid)?;
+        let match_id = parts.next().ok_or_else(invalid)?.to_string();
+        let start_time = parts
+            .next()
+            .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+            .ok_or_else(invalid)?
+            .with_timezone(&Utc);
+
+        Ok(MatchCursor {
+            offset,
+            match_id,
+            start_time,
+        })
+    }
+
+    fn encode_cursor(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}:{}",
+            self.offset,
+            self.match_id,
+            self.start_time.to_rfc3339()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::halo_requests::tests::{at, page};
+
+    fn cursor(offset: usize, match_id: &str, start_time: DateTime<Utc>) -> MatchCursor {
+        MatchCursor {
+            offset,
+            match_id: match_id.to_string(),
+            start_time,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let decoded = MatchCursor::decode_cursor(
+            &cursor(42, "5fa9e0a1-2b2d-4d43-9f0c-0e1e2c4c7b1a", at(0)).encode_cursor(),
+        )
+        .unwrap();
+
+        assert_eq!(decoded.offset, 42);
+        assert_eq!(decoded.match_id, "5fa9e0a1-2b2d-4d43-9f0c-0e1e2c4c7b1a");
+        assert_eq!(decoded.start_time, at(0));
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let encode = |x: &str| URL_SAFE_NO_PAD.encode(x);
+
+        for s in [
+            "not base64!".to_string(),
+            encode(""),
+            encode("x:id:2023-10-01T12:00:00+00:00"),
+            encode("-1:id:2023-10-01T12:00:00+00:00"),
+            encode("1:id"),
+            encode("1:id:yesterday"),
+        ] {
+            assert!(MatchCursor::decode_cursor(&s).is_err(), "{s} decoded");
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_offsets() {
+        let at_limit = cursor(MAX_OFFSET, "id", at(0)).encode_cursor();
+        let past_limit = cursor(MAX_OFFSET + 1, "id", at(0)).encode_cursor();
+        let overflowing =
+            URL_SAFE_NO_PAD.encode(format!("{}:id:{}", usize::MAX, at(0).to_rfc3339()));
+
+        assert!(MatchCursor::decode_cursor(&at_limit).is_ok());
+        assert!(MatchCursor::decode_cursor(&past_limit).is_err());
+        assert!(MatchCursor::decode_cursor(&overflowing).is_err());
+    }
+
+    #[test]
+    fn locates_the_anchor() {
+        let resolved = cursor(3, "m7", at(7))
+            .locate(25, &page(&[9, 8, 7, 6]))
+            .unwrap();
+
+        assert_eq!(resolved.offset, 27);
+        assert!(resolved.found);
+        assert_eq!(resolved.after(), 28);
+        assert_eq!(resolved.before(), 27);
+    }
+
+    #[test]
+    fn locates_where_a_missing_anchor_was() {
+        let resolved = cursor(0, "gone", at(7))
+            .locate(0, &page(&[9, 8, 6, 5]))
+            .unwrap();
+
+        assert_eq!(resolved.offset, 2);
+        assert!(!resolved.found);
+        assert_eq!(resolved.after(), 2);
+        assert_eq!(resolved.before(), 2);
+    }
+
+    #[test]
+    fn locates_past_the_end_of_history() {
+        let resolved = cursor(0, "m1", at(1)).locate(50, &page(&[9, 8])).unwrap();
+
+        assert_eq!(resolved.offset, 52);
+        assert!(!resolved.found);
+    }
+
+    #[test]
+    fn keeps_looking_past_full_pages() {
+        let minutes = (100..100 + halo_requests::MATCHES_PAGE_SIZE as i64)
+            .rev()
+            .collect::<Vec<_>>();
+
+        assert!(cursor(0, "m1", at(1)).locate(0, &page(&minutes)).is_none());
+    }
+}
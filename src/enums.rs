@@ -0,0 +1,291 @@
+use async_graphql::Enum;
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    Tie,
+    Win,
+    Loss,
+    DidNotFinish,
+    Unknown,
+}
+
+impl From<i32> for Outcome {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => Outcome::Tie,
+            2 => Outcome::Win,
+            3 => Outcome::Loss,
+            4 => Outcome::DidNotFinish,
+            _ => Outcome::Unknown,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum LifecycleMode {
+    Custom,
+    Local,
+    Matchmade,
+    Unknown,
+}
+
+impl From<i32> for LifecycleMode {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => LifecycleMode::Custom,
+            2 => LifecycleMode::Local,
+            3 => LifecycleMode::Matchmade,
+            _ => LifecycleMode::Unknown,
+        }
+    }
+}
+
+impl LifecycleMode {
+    /// Name used in upstream paths such as the service record endpoint
+    pub fn path_name(&self) -> Option<&'static str> {
+        match self {
+            LifecycleMode::Custom => Some("Custom"),
+            LifecycleMode::Local => Some("Local"),
+            LifecycleMode::Matchmade => Some("Matchmade"),
+            LifecycleMode::Unknown => None,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GameVariantCategory {
+    Campaign,
+    Forge,
+    Academy,
+    AcademyTutorial,
+    AcademyPractice,
+    Slayer,
+    Attrition,
+    Elimination,
+    Fiesta,
+    Swat,
+    Strongholds,
+    Bastion,
+    KingOfTheHill,
+    TotalControl,
+    Ctf,
+    Assault,
+    Extraction,
+    Oddball,
+    Stockpile,
+    Juggernaut,
+    Regicide,
+    Infection,
+    Escort,
+    GunGame,
+    Grifball,
+    Race,
+    LandGrab,
+    Minigame,
+    Firefight,
+    Unknown,
+}
+
+impl From<i32> for GameVariantCategory {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => GameVariantCategory::Campaign,
+            1 => GameVariantCategory::Forge,
+            2 => GameVariantCategory::Academy,
+            3 => GameVariantCategory::AcademyTutorial,
+            4 => GameVariantCategory::AcademyPractice,
+            6 => GameVariantCategory::Slayer,
+            7 => GameVariantCategory::Attrition,
+            8 => GameVariantCategory::Elimination,
+            9 => GameVariantCategory::Fiesta,
+            10 => GameVariantCategory::Swat,
+            11 => GameVariantCategory::Strongholds,
+            12 => GameVariantCategory::Bastion,
+            13 => GameVariantCategory::KingOfTheHill,
+            14 => GameVariantCategory::TotalControl,
+            15 => GameVariantCategory::Ctf,
+            16 => GameVariantCategory::Assault,
+            17 => GameVariantCategory::Extraction,
+            18 => GameVariantCategory::Oddball,
+            19 => GameVariantCategory::Stockpile,
+            20 => GameVariantCategory::Juggernaut,
+            21 => GameVariantCategory::Regicide,
+            22 => GameVariantCategory::Infection,
+            23 => GameVariantCategory::Escort,
+            24 => GameVariantCategory::GunGame,
+            25 => GameVariantCategory::Grifball,
+            26 => GameVariantCategory::Race,
+            39 => GameVariantCategory::LandGrab,
+            41 => GameVariantCategory::Minigame,
+            42 => GameVariantCategory::Firefight,
+            _ => GameVariantCategory::Unknown,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GameplayInteraction {
+    Pvp,
+    Pve,
+    Unknown,
+}
+
+impl From<i32> for GameplayInteraction {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => GameplayInteraction::Pvp,
+            2 => GameplayInteraction::Pve,
+            _ => GameplayInteraction::Unknown,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum PlayerType {
+    Human,
+    Bot,
+    Unknown,
+}
+
+impl From<i32> for PlayerType {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => PlayerType::Human,
+            2 => PlayerType::Bot,
+            _ => PlayerType::Unknown,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum BotDifficulty {
+    Recruit,
+    Marine,
+    Odst,
+    Spartan,
+    Unknown,
+}
+
+impl From<i32> for BotDifficulty {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => BotDifficulty::Recruit,
+            1 => BotDifficulty::Marine,
+            2 => BotDifficulty::Odst,
+            3 => BotDifficulty::Spartan,
+            _ => BotDifficulty::Unknown,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum BotType {
+    Spartan,
+    Unknown,
+}
+
+impl From<i32> for BotType {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => BotType::Spartan,
+            _ => BotType::Unknown,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum AssetKind {
+    Film,
+    Map,
+    Playlist,
+    Prefab,
+    TestAsset,
+    UgcGameVariant,
+    MapModePair,
+    Project,
+    Manifest,
+    EngineGameVariant,
+    Unknown,
+}
+
+impl From<i32> for AssetKind {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => AssetKind::Film,
+            2 => AssetKind::Map,
+            3 => AssetKind::Playlist,
+            4 => AssetKind::Prefab,
+            5 => AssetKind::TestAsset,
+            6 => AssetKind::UgcGameVariant,
+            7 => AssetKind::MapModePair,
+            8 => AssetKind::Project,
+            9 => AssetKind::Manifest,
+            10 => AssetKind::EngineGameVariant,
+            _ => AssetKind::Unknown,
+        }
+    }
+}
+
+impl AssetKind {
+    /// Name used in UGC discovery paths
+    pub fn path_name(&self) -> Option<&'static str> {
+        match self {
+            AssetKind::Film => Some("films"),
+            AssetKind::Map => Some("maps"),
+            AssetKind::Playlist => Some("playlists"),
+            AssetKind::Prefab => Some("prefabs"),
+            AssetKind::UgcGameVariant => Some("ugcGameVariants"),
+            AssetKind::MapModePair => Some("mapModePairs"),
+            AssetKind::Project => Some("projects"),
+            AssetKind::EngineGameVariant => Some("engineGameVariants"),
+            AssetKind::TestAsset | AssetKind::Manifest | AssetKind::Unknown => None,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum CareerRankTier {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+    Onyx,
+    Hero,
+    Unknown,
+}
+
+impl From<&str> for CareerRankTier {
+    fn from(value: &str) -> Self {
+        match value {
+            "Bronze" => CareerRankTier::Bronze,
+            "Silver" => CareerRankTier::Silver,
+            "Gold" => CareerRankTier::Gold,
+            "Platinum" => CareerRankTier::Platinum,
+            "Diamond" => CareerRankTier::Diamond,
+            "Onyx" => CareerRankTier::Onyx,
+            "Hero" => CareerRankTier::Hero,
+            _ => CareerRankTier::Unknown,
+        }
+    }
+}
+
+/// Match history type filter accepted by the matches endpoint
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MatchType {
+    All,
+    Matchmaking,
+    Custom,
+    Local,
+}
+
+impl From<MatchType> for i32 {
+    fn from(value: MatchType) -> Self {
+        match value {
+            MatchType::All => 0,
+            MatchType::Matchmaking => 1,
+            MatchType::Custom => 2,
+            MatchType::Local => 3,
+        }
+    }
+}
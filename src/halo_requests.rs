@@ -1,10 +1,15 @@
+use crate::cache::TtlCache;
+use crate::enums::{MatchType, PlayerType};
+use crate::scalars::Duration;
 use async_graphql::Result;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct MatchesResponse {
     pub count: i32,
@@ -14,8 +19,8 @@ pub struct MatchesResponse {
     pub results: Vec<Match>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct Match {
     pub last_team_id: i32,
     pub match_id: String,
@@ -25,51 +30,106 @@ pub struct Match {
     pub rank: i32,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchInfo {
     pub clearance_id: String,
-    pub duration: String,
-    pub end_time: String,
+    pub duration: Duration,
+    pub end_time: DateTime<Utc>,
     pub game_variant_category: i32,
     pub gameplay_interaction: i32,
     pub level_id: String,
     pub lifecycle_mode: i32,
     pub map_variant: AssetReference,
-    pub playable_duration: String,
-    pub playlist: Option<Value>,
+    pub playable_duration: Duration,
+    pub playlist: Option<AssetReference>,
     pub playlist_experience: Option<Value>,
-    pub playlist_map_mode_pair: Option<Value>,
+    pub playlist_map_mode_pair: Option<AssetReference>,
     pub season_id: Option<Value>,
-    pub start_time: String,
+    pub start_time: DateTime<Utc>,
     pub team_scoring_enabled: bool,
     pub teams_enabled: bool,
     pub ugc_game_variant: AssetReference,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct AssetReference {
     pub asset_id: String,
     pub asset_kind: i32,
     pub version_id: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Asset {
+    pub asset_id: String,
+    pub version_id: String,
+    pub version_number: Option<i32>,
+    pub public_name: String,
+    pub description: String,
+    pub files: AssetFiles,
+    pub tags: Vec<String>,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct AssetFiles {
+    pub prefix: String,
+    pub file_relative_paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Film {
+    pub asset_id: String,
+    pub blob_storage_path_prefix: String,
+    pub custom_data: FilmCustomData,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct FilmCustomData {
+    pub film_length: i64,
+    pub chunks: Vec<FilmChunk>,
+    pub has_game_ended: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct FilmChunk {
+    pub index: i32,
+    pub chunk_start_time_offset_milliseconds: i64,
+    pub duration_milliseconds: i64,
+    pub chunk_size: i64,
+    pub file_relative_path: String,
+    pub chunk_type: i32,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 struct SkillResponse {
     value: Vec<Skill>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResultRankRecap {
     pub pre_match_csr: SkillResultRankRecapCsr,
     pub post_match_csr: SkillResultRankRecapCsr,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResultRankRecapCsr {
     pub value: i32,
     pub measurement_matches_remaining: i32,
@@ -89,30 +149,30 @@ pub struct SkillResultStatPerformances {
     pub deaths: Option<SkillResultStatPerformance>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResultStatPerformance {
     pub count: i32,
     pub expected: f32,
     pub std_dev: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResultCounterfactuals {
     pub self_counterfactuals: SkillResultCounterfactualsKillsDeaths,
     pub tier_counterfactuals: SkillResultCounterfactualsTierCounterfactuals,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResultCounterfactualsKillsDeaths {
     pub kills: f32,
     pub deaths: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResultCounterfactualsTierCounterfactuals {
     pub bronze: SkillResultCounterfactualsKillsDeaths,
     pub silver: SkillResultCounterfactualsKillsDeaths,
@@ -122,8 +182,8 @@ pub struct SkillResultCounterfactualsTierCounterfactuals {
     pub onyx: SkillResultCounterfactualsKillsDeaths,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct SkillResult {
     pub team_mmr: f32,
     pub rank_recap: SkillResultRankRecap,
@@ -132,6 +192,9 @@ pub struct SkillResult {
     pub team_mmrs: HashMap<String, f32>,
     pub ranked_rewards: Option<Value>,
     pub counterfactuals: SkillResultCounterfactuals,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -139,17 +202,247 @@ pub struct SkillResult {
 pub struct Skill {
     pub id: String,
     pub result_code: i32,
+    #[serde(default)]
     pub result: SkillResult,
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlaylistCsrResponse {
+    value: Vec<PlaylistCsr>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlaylistCsr {
+    pub id: String,
+    pub result_code: i32,
+    #[serde(default)]
+    pub result: PlaylistCsrResult,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct PlaylistCsrResult {
+    pub current: SkillResultRankRecapCsr,
+    pub season_max: SkillResultRankRecapCsr,
+    pub all_time_max: SkillResultRankRecapCsr,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MedalMetadata {
+    pub difficulties: Vec<String>,
+    pub types: Vec<String>,
+    pub sprites: HashMap<String, MedalMetadataSprite>,
+    pub medals: Vec<MedalMetadataMedal>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MedalMetadataSprite {
+    pub path: String,
+    pub size: i32,
+    pub columns: i32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MedalMetadataMedal {
+    pub name_id: i64,
+    pub name: GamecmsText,
+    pub description: GamecmsText,
+    pub sprite_index: i32,
+    pub sorting_weight: i32,
+    pub difficulty_index: usize,
+    pub type_index: usize,
+    pub personal_score: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct GamecmsText {
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct RewardTrack {
+    pub reward_track_path: String,
+    pub track_type: String,
+    pub current_progress: RewardTrackProgress,
+    pub previous_progress: Option<RewardTrackProgress>,
+    pub is_owned: bool,
+    pub base_xp: Option<i32>,
+    pub boost_xp: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct RewardTrackProgress {
+    pub rank: i32,
+    pub partial_progress: i32,
+    pub is_owned: bool,
+    pub has_reached_max_rank: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct OperationRewardTracks {
+    pub operation_reward_tracks: Vec<RewardTrack>,
+    pub active_operation_reward_track_path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct CareerRankMetadata {
+    pub ranks: Vec<CareerRankMetadataRank>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct CareerRankMetadataRank {
+    pub rank: i32,
+    pub rank_title: GamecmsText,
+    pub rank_sub_title: GamecmsText,
+    pub tier_type: String,
+    pub rank_grade: i32,
+    pub xp_required_for_rank: i32,
+    pub rank_icon: String,
+    pub rank_large_icon: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct SeasonCalendar {
+    pub seasons: Vec<SeasonCalendarEntry>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct SeasonCalendarEntry {
+    pub csr_season_file_path: String,
+    pub operation_track_path: String,
+    /// Progression file path, also used as the season id in match stats
+    pub season_metadata: String,
+    pub start_date: GamecmsDate,
+    pub end_date: GamecmsDate,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct GamecmsDate {
+    #[serde(rename = "ISO8601Date")]
+    pub iso8601_date: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct SeasonMetadata {
+    pub name: GamecmsText,
+    pub description: GamecmsText,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct PlayerCustomization {
+    pub appearance: AppearanceCustomization,
+    pub cores: ArmorCores,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct AppearanceCustomization {
+    pub service_tag: String,
+    pub emblem: EmblemCustomization,
+    pub backdrop_image_path: String,
+    pub player_title_path: String,
+    pub action_pose_path: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct EmblemCustomization {
+    pub emblem_path: String,
+    pub configuration_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ArmorCores {
+    pub cores: Vec<ArmorCore>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ArmorCore {
+    pub core_id: String,
+    pub core_type: String,
+    pub is_equipped: bool,
+    pub themes: Vec<ArmorTheme>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ArmorTheme {
+    pub is_equipped: bool,
+    pub is_default: bool,
+    pub theme_path: String,
+}
+
+/// Emblem and nameplate images keyed by emblem path, then by configuration id
+pub type EmblemMapping = HashMap<String, HashMap<String, EmblemMappingEntry>>;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EmblemMappingEntry {
+    pub emblem_cms_path: String,
+    pub nameplate_cms_path: String,
+    pub text_color: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct InventoryItem {
+    pub common_data: InventoryItemCommonData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct InventoryItemCommonData {
+    pub title: GamecmsText,
+    pub description: GamecmsText,
+    pub display_path: GamecmsMedia,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct GamecmsMedia {
+    pub media: GamecmsMediaFile,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct GamecmsMediaFile {
+    pub media_url: GamecmsMediaUrl,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct GamecmsMediaUrl {
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct Gamer {
     pub xuid: String,
     pub gamertag: String,
     pub gamerpic: GamerPic,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct GamerPic {
     pub small: String,
     pub medium: String,
@@ -157,8 +450,8 @@ pub struct GamerPic {
     pub xlarge: String,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsTeam {
     pub team_id: i32,
     pub outcome: i32,
@@ -166,16 +459,16 @@ pub struct MatchStatsTeam {
     pub stats: MatchStatsTeamStats,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsTeamStatsCoreStatsScore {
     pub name_id: i64,
     pub count: i32,
     pub total_personal_score_awarded: i32,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsTeamStatsCoreStats {
     pub score: i32,
     pub personal_score: i32,
@@ -189,7 +482,7 @@ pub struct MatchStatsTeamStatsCoreStats {
     pub kda: f32,
     pub suicides: i32,
     pub betrayals: i32,
-    pub average_life_duration: String,
+    pub average_life_duration: Duration,
     pub grenade_kills: i32,
     pub headshot_kills: i32,
     pub melee_kills: i32,
@@ -211,62 +504,197 @@ pub struct MatchStatsTeamStatsCoreStats {
     pub deprecated_damage_taken: f32,
     pub spawns: i32,
     pub objectives_completed: i32,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsTeamStatsZonesStats {
     pub stronghold_captures: i32,
     pub stronghold_defensive_kills: i32,
     pub stronghold_offensive_kills: i32,
     pub stronghold_secures: i32,
-    pub stronghold_occupation_time: String,
+    pub stronghold_occupation_time: Duration,
     pub stronghold_scoring_ticks: i32,
+    pub zone_captures: i32,
+    pub zone_defensive_kills: i32,
+    pub zone_offensive_kills: i32,
+    pub zone_secures: i32,
+    pub zone_occupation_time: Duration,
+    pub zone_scoring_ticks: i32,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct MatchStatsTeamStatsCaptureTheFlagStats {
+    pub flag_capture_assists: i32,
+    pub flag_captures: i32,
+    pub flag_carriers_killed: i32,
+    pub flag_grabs: i32,
+    pub flag_returners_killed: i32,
+    pub flag_returns: i32,
+    pub flag_secures: i32,
+    pub flag_steals: i32,
+    pub kills_as_flag_carrier: i32,
+    pub kills_as_flag_returner: i32,
+    pub time_as_flag_carrier: Duration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct MatchStatsTeamStatsOddballStats {
+    pub kills_as_skull_carrier: i32,
+    pub longest_time_as_skull_carrier: Duration,
+    pub skull_carriers_killed: i32,
+    pub skull_grabs: i32,
+    pub time_as_skull_carrier: Duration,
+    pub skull_scoring_ticks: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct MatchStatsTeamStatsEliminationStats {
+    pub allies_revived: i32,
+    pub elimination_assists: i32,
+    pub eliminations: i32,
+    pub enemy_revives_denied: i32,
+    pub executions: i32,
+    pub killed_in_order: i32,
+    pub last_player_standing_kills: i32,
+    pub rounds_survived: i32,
+    pub times_revived_by_ally: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct MatchStatsTeamStatsExtractionStats {
+    pub successful_extractions: i32,
+    pub extraction_conversions_denied: i32,
+    pub extraction_conversions_completed: i32,
+    pub extraction_initiations_denied: i32,
+    pub extraction_initiations_completed: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct MatchStatsTeamStatsInfectionStats {
+    pub alphas_killed: i32,
+    pub spartans_infected: i32,
+    pub spartans_infected_as_alpha: i32,
+    pub kills_as_last_spartan_standing: i32,
+    pub last_spartan_standing_infected: i32,
+    pub rounds_as_alpha: i32,
+    pub rounds_as_last_spartan_standing: i32,
+    pub rounds_finished_as_infected: i32,
+    pub rounds_survived_as_spartan: i32,
+    pub rounds_survived_as_last_spartan_standing: i32,
+    pub time_as_last_spartan_standing: Duration,
+    pub infected_killed: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct MatchStatsTeamStatsStockpileStats {
+    pub kills_as_power_seed_carrier: i32,
+    pub power_seed_carriers_killed: i32,
+    pub power_seeds_deposited: i32,
+    pub power_seeds_stolen: i32,
+    pub time_as_power_seed_carrier: Duration,
+    pub time_as_power_seed_driver: Duration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsTeamStats {
     pub core_stats: MatchStatsTeamStatsCoreStats,
     pub zones_stats: Option<MatchStatsTeamStatsZonesStats>,
+    pub capture_the_flag_stats: Option<MatchStatsTeamStatsCaptureTheFlagStats>,
+    pub oddball_stats: Option<MatchStatsTeamStatsOddballStats>,
+    pub elimination_stats: Option<MatchStatsTeamStatsEliminationStats>,
+    pub extraction_stats: Option<MatchStatsTeamStatsExtractionStats>,
+    pub infection_stats: Option<MatchStatsTeamStatsInfectionStats>,
+    pub stockpile_stats: Option<MatchStatsTeamStatsStockpileStats>,
+    /// Stat blocks of modes not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsPlayerParticipationInfo {
-    pub first_joined_time: String,
-    pub last_leave_time: Option<String>,
+    pub first_joined_time: DateTime<Utc>,
+    pub last_leave_time: Option<DateTime<Utc>>,
     pub present_at_beginning: bool,
     pub joined_in_progress: bool,
     pub left_in_progress: bool,
     pub present_at_completion: bool,
-    pub time_played: String,
-    pub confirmed_participation: Option<Value>,
+    pub time_played: Duration,
+    pub confirmed_participation: Option<ConfirmedParticipation>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct BotAttributes {
+    pub difficulty: i32,
+    /// Absent for matches recorded before bots had types
+    pub bot_type: Option<i32>,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
+}
+
+/// Sent either as a bare flag or as an object describing the confirmation
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ConfirmedParticipation {
+    Flag(bool),
+    Details(ConfirmedParticipationDetails),
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ConfirmedParticipationDetails {
+    /// Assumed when the object leaves it out
+    pub confirmed: Option<bool>,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsPlayer {
     pub player_id: String,
     pub player_type: i32,
-    pub bot_attributes: Option<Value>,
+    pub bot_attributes: Option<BotAttributes>,
     pub last_team_id: i32,
     pub outcome: i32,
     pub rank: i32,
     pub participation_info: MatchStatsPlayerParticipationInfo,
     /// Stats for the player when playing on each team
     pub player_team_stats: Vec<MatchStatsPlayerPlayerTeamStat>,
+    /// Upstream fields not modelled above
+    #[serde(flatten, skip_serializing)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+impl MatchStatsPlayer {
+    pub fn is_bot(&self) -> bool {
+        PlayerType::from(self.player_type) == PlayerType::Bot || self.bot_attributes.is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStatsPlayerPlayerTeamStat {
     pub team_id: i32,
     pub stats: MatchStatsTeamStats,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
 pub struct MatchStats {
     pub match_id: String,
     pub match_info: MatchInfo,
@@ -274,83 +702,708 @@ pub struct MatchStats {
     pub players: Vec<MatchStatsPlayer>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ServiceRecord {
+    pub time_played: Duration,
+    pub matches_completed: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub ties: i32,
+    pub core_stats: MatchStatsTeamStatsCoreStats,
+    pub zones_stats: Option<MatchStatsTeamStatsZonesStats>,
+}
+
+/// Decodes a response body, logging the path of any field that had to be
+/// defaulted or was not recognised. Error statuses fail before decoding, so
+/// an error body never turns into a defaulted value.
+async fn decode<T: DeserializeOwned + Serialize>(response: Response) -> Result<T> {
+    decode_value(&raw_body(response).await?)
+}
+
+async fn raw_body(response: Response) -> Result<Value> {
+    // Keeps the status around for `is_unauthorized`
+    let response = response
+        .error_for_status()
+        .map_err(async_graphql::Error::new_with_source)?;
+
+    Ok(response.json::<Value>().await?)
+}
+
+/// Whether a request failed because upstream turned the Spartan token down.
+pub fn is_unauthorized(err: &async_graphql::Error) -> bool {
+    err.source
+        .as_ref()
+        .and_then(|x| x.downcast_ref::<reqwest::Error>())
+        .and_then(|x| x.status())
+        .is_some_and(|x| x == StatusCode::UNAUTHORIZED)
+}
+
+/// Decodes raw upstream JSON, such as a response archived as it was sent.
+pub fn decode_value<T: DeserializeOwned + Serialize>(raw: &Value) -> Result<T> {
+    let decoded: T = serde_path_to_error::deserialize(raw).map_err(|err| {
+        println!("Decode error at {}: {}", err.path(), err.inner());
+        async_graphql::Error::new(format!("Failed to decode {}: {}", err.path(), err.inner()))
+    })?;
+
+    log_decode_warnings("", raw, &serde_json::to_value(&decoded)?);
+
+    Ok(decoded)
+}
+
+/// Decode warnings already logged. Paths leave out array indices, so each
+/// field is reported once however many entries or responses it shows up in.
+static LOGGED_DECODE_WARNINGS: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn log_decode_warning(warning: String) {
+    if LOGGED_DECODE_WARNINGS
+        .lock()
+        .unwrap()
+        .insert(warning.clone())
+    {
+        println!("Decode warning: {warning}");
+    }
+}
+
+fn log_decode_warnings(path: &str, raw: &Value, decoded: &Value) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+
+    match (raw, decoded) {
+        (Value::Object(raw), Value::Object(decoded)) => {
+            for (key, value) in decoded {
+                match raw.get(key) {
+                    Some(raw_value) => log_decode_warnings(&join(key), raw_value, value),
+                    None if !value.is_null() => {
+                        log_decode_warning(format!("missing field {}, using default", join(key)))
+                    }
+                    None => {}
+                }
+            }
+
+            for key in raw.keys().filter(|key| !decoded.contains_key(*key)) {
+                log_decode_warning(format!("unexpected field {}", join(key)));
+            }
+        }
+        (Value::Array(raw), Value::Array(decoded)) => {
+            for (raw, decoded) in raw.iter().zip(decoded) {
+                log_decode_warnings(&format!("{path}[]"), raw, decoded);
+            }
+        }
+        _ => {}
+    }
+}
+
 pub async fn matches(
     client: &Client,
     spartan_token: &str,
     xuid: &str,
+    match_type: Option<MatchType>,
     start: Option<usize>,
     count: Option<usize>,
 ) -> Result<MatchesResponse> {
-    Ok(client
+    let res = client
         .get(format!(
             "https://halostats.svc.halowaypoint.com/hi/players/xuid({xuid})/matches"
         ))
         .query(&[("start", start), ("count", count)])
+        .query(&[("type", match_type.map(i32::from))])
         .header("x-343-authorization-spartan", spartan_token)
         .header("Accept", "application/json")
         .send()
+        .await?;
+
+    decode(res).await
+}
+
+/// Base url for Waypoint files served by gamecms.
+pub const GAMECMS_FILE_URL: &str = "https://gamecms-hacs.svc.halowaypoint.com/hi/Waypoint/file";
+
+/// Base url for progression files, such as reward tracks and the season
+/// calendar, served by gamecms.
+pub const GAMECMS_PROGRESSION_URL: &str =
+    "https://gamecms-hacs.svc.halowaypoint.com/hi/Progression/file";
+
+/// Base url for images served by gamecms.
+pub const GAMECMS_IMAGE_URL: &str = "https://gamecms-hacs.svc.halowaypoint.com/hi/images/file";
+
+/// Largest page the matches endpoint will return.
+pub const MATCHES_PAGE_SIZE: usize = 25;
+
+/// Most recent page of each player's match history, keyed by xuid and
+/// history type.
+pub type FirstPageCache = TtlCache<(String, Option<MatchType>), MatchesResponse>;
+
+/// Most recent page of match history, shared through `first_pages`. Only
+/// for lookups that stop at this page, as it may be older than live pages
+/// further down.
+pub async fn first_page(
+    client: &Client,
+    spartan_token: &str,
+    first_pages: &Arc<FirstPageCache>,
+    xuid: &str,
+    match_type: Option<MatchType>,
+) -> Result<MatchesResponse> {
+    let client = client.clone();
+    let spartan_token = spartan_token.to_string();
+    let owned_xuid = xuid.to_string();
+
+    first_pages
+        .get_or_fetch((xuid.to_string(), match_type), move || async move {
+            matches(
+                &client,
+                &spartan_token,
+                &owned_xuid,
+                match_type,
+                Some(0),
+                Some(MATCHES_PAGE_SIZE),
+            )
+            .await
+        })
+        .await
+}
+
+/// Fetches `count` matches starting at `start`, splitting the range into
+/// concurrent upstream requests.
+pub async fn matches_range(
+    client: &Client,
+    spartan_token: &str,
+    xuid: &str,
+    match_type: Option<MatchType>,
+    start: usize,
+    count: usize,
+) -> Result<Vec<Match>> {
+    let pages = (start..start + count)
+        .step_by(MATCHES_PAGE_SIZE)
+        .map(|page_start| {
+            let page_count = MATCHES_PAGE_SIZE.min(start + count - page_start);
+            matches(
+                client,
+                spartan_token,
+                xuid,
+                match_type,
+                Some(page_start),
+                Some(page_count),
+            )
+        });
+
+    Ok(futures::future::try_join_all(pages)
         .await?
-        .json::<MatchesResponse>()
-        .await?)
+        .into_iter()
+        .flat_map(|x| x.results)
+        .collect())
+}
+
+/// Finds the number of matches in a player's history.
+pub async fn matches_len(
+    client: &Client,
+    spartan_token: &str,
+    xuid: &str,
+    match_type: Option<MatchType>,
+) -> Result<usize> {
+    let page_len = |start: usize| async move {
+        matches(
+            client,
+            spartan_token,
+            xuid,
+            match_type,
+            Some(start),
+            Some(MATCHES_PAGE_SIZE),
+        )
+        .await
+        .map(|x| x.results.len())
+    };
+
+    history_len(page_len).await
+}
+
+/// Finds the length of a history given the length of the page starting at
+/// an offset.
+async fn history_len<F, Fut>(page_len: F) -> Result<usize>
+where
+    F: Fn(usize) -> Fut,
+    Fut: std::future::Future<Output = Result<usize>>,
+{
+    // Double the probe offset until it passes the end, then binary search for
+    // the last page.
+    let mut low = 0;
+    let mut high = MATCHES_PAGE_SIZE;
+
+    loop {
+        match page_len(high).await? {
+            0 => break,
+            len if len < MATCHES_PAGE_SIZE => return Ok(high + len),
+            _ => {
+                low = high;
+                high *= 2;
+            }
+        }
+    }
+
+    while high - low > MATCHES_PAGE_SIZE {
+        let mid = low + (high - low) / 2;
+
+        match page_len(mid).await? {
+            0 => high = mid,
+            len if len < MATCHES_PAGE_SIZE => return Ok(mid + len),
+            _ => low = mid,
+        }
+    }
+
+    Ok(low + page_len(low).await?)
 }
 
 pub async fn gamer(client: &Client, spartan_token: &str, gamertag: &str) -> Result<Gamer> {
-    Ok(client
+    let res = client
         .get(format!(
             "https://profile.svc.halowaypoint.com/users/gt({gamertag})"
         ))
         .header("x-343-authorization-spartan", spartan_token)
         .header("Accept", "application/json")
         .send()
-        .await?
-        .json::<Gamer>()
-        .await?)
+        .await?;
+
+    decode(res).await
 }
 
+/// Profile of whoever the token was issued to
+pub async fn me(client: &Client, spartan_token: &str) -> Result<Gamer> {
+    let res = client
+        .get("https://profile.svc.halowaypoint.com/users/me")
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn gamer_by_xuid(client: &Client, spartan_token: &str, xuid: &str) -> Result<Gamer> {
+    let res = client
+        .get(format!(
+            "https://profile.svc.halowaypoint.com/users/xuid({xuid})"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+/// Skill results of each player, along with the raw JSON of each for archiving
 pub async fn skill(
     client: &Client,
     spartan_token: &str,
     match_id: &str,
     xuids: &[String],
-) -> Result<Vec<Skill>> {
-    println!(
-        "URL: {}",
-        format!(
-            "https://skill.svc.halowaypoint.com/hi/matches/{match_id}/skill?players={}",
-            xuids
-                .iter()
-                .map(|x| format!("xuid({}),", x))
-                .collect::<String>()
-                .trim_end_matches(",")
-        )
+) -> Result<Vec<(Skill, Value)>> {
+    let url = format!(
+        "https://skill.svc.halowaypoint.com/hi/matches/{match_id}/skill?players={}",
+        xuids
+            .iter()
+            .map(|x| format!("xuid({}),", x))
+            .collect::<String>()
+            .trim_end_matches(',')
     );
-    Ok(client
+
+    println!("URL: {url}");
+
+    let res = client
+        .get(url)
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    let raw = raw_body(res).await?;
+    let decoded = decode_value::<SkillResponse>(&raw)?;
+    let raw = raw
+        .get("Value")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    Ok(decoded.value.into_iter().zip(raw).collect())
+}
+
+/// Match stats, along with the raw JSON they were decoded from for archiving
+pub async fn stats(
+    client: &Client,
+    spartan_token: &str,
+    match_id: &str,
+) -> Result<(MatchStats, Value)> {
+    let res = client
+        .get(format!(
+            "https://halostats.svc.halowaypoint.com/hi/matches/{match_id}/stats",
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    let raw = raw_body(res).await?;
+
+    Ok((decode_value(&raw)?, raw))
+}
+
+pub async fn service_record(
+    client: &Client,
+    spartan_token: &str,
+    xuid: &str,
+    mode: &str,
+    season_id: Option<&str>,
+    playlist_id: Option<&str>,
+) -> Result<ServiceRecord> {
+    let res = client
+        .get(format!(
+            "https://halostats.svc.halowaypoint.com/hi/players/xuid({xuid})/{mode}/servicerecord"
+        ))
+        .query(&[("seasonId", season_id), ("playlistAssetId", playlist_id)])
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn playlist_csr(
+    client: &Client,
+    spartan_token: &str,
+    playlist_id: &str,
+    xuids: &[String],
+) -> Result<Vec<PlaylistCsr>> {
+    let res = client
         .get(format!(
-            "https://skill.svc.halowaypoint.com/hi/matches/{match_id}/skill?players={}",
+            "https://skill.svc.halowaypoint.com/hi/playlist/{playlist_id}/csrs?players={}",
             xuids
                 .iter()
                 .map(|x| format!("xuid({}),", x))
                 .collect::<String>()
-                .trim_end_matches(",")
+                .trim_end_matches(',')
         ))
         .header("x-343-authorization-spartan", spartan_token)
         .header("Accept", "application/json")
         .send()
-        .await?
-        .json::<SkillResponse>()
-        .await?
-        .value)
+        .await?;
+
+    Ok(decode::<PlaylistCsrResponse>(res).await?.value)
 }
 
-pub async fn stats(client: &Client, spartan_token: &str, match_id: &str) -> Result<MatchStats> {
-    Ok(client
+pub async fn asset(
+    client: &Client,
+    spartan_token: &str,
+    kind: &str,
+    asset_id: &str,
+    version_id: &str,
+) -> Result<Asset> {
+    let res = client
         .get(format!(
-            "https://halostats.svc.halowaypoint.com/hi/matches/{match_id}/stats",
+            "https://discovery-infiniteugc.svc.halowaypoint.com/hi/{kind}/{asset_id}/versions/{version_id}"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+/// Theater film recorded for a match, if there is one
+pub async fn film(client: &Client, spartan_token: &str, match_id: &str) -> Result<Option<Film>> {
+    let res = client
+        .get(format!(
+            "https://discovery-infiniteugc.svc.halowaypoint.com/hi/films/matches/{match_id}/spectate"
         ))
         .header("x-343-authorization-spartan", spartan_token)
         .header("Accept", "application/json")
         .send()
+        .await?;
+
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    decode(res).await.map(Some)
+}
+
+/// Starts downloading a film chunk, leaving the body to be streamed by the
+/// caller.
+pub async fn film_chunk(
+    client: &Client,
+    spartan_token: &str,
+    film: &Film,
+    chunk: &FilmChunk,
+) -> reqwest::Result<Response> {
+    client
+        .get(format!(
+            "{}/{}",
+            film.blob_storage_path_prefix.trim_end_matches('/'),
+            chunk.file_relative_path.trim_start_matches('/')
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .send()
         .await?
-        .json::<MatchStats>()
-        .await?)
+        .error_for_status()
+}
+
+pub async fn medal_metadata(client: &Client, spartan_token: &str) -> Result<MedalMetadata> {
+    let res = client
+        .get(format!("{GAMECMS_FILE_URL}/medals/metadata.json"))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn career_rank(client: &Client, spartan_token: &str, xuid: &str) -> Result<RewardTrack> {
+    let res = client
+        .get(format!(
+            "https://economy.svc.halowaypoint.com/hi/players/xuid({xuid})/rewardtracks/careerranks/careerrank1"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn operations(
+    client: &Client,
+    spartan_token: &str,
+    xuid: &str,
+) -> Result<OperationRewardTracks> {
+    let res = client
+        .get(format!(
+            "https://economy.svc.halowaypoint.com/hi/players/xuid({xuid})/operations"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn career_rank_metadata(
+    client: &Client,
+    spartan_token: &str,
+) -> Result<CareerRankMetadata> {
+    let res = client
+        .get(format!(
+            "{GAMECMS_PROGRESSION_URL}/RewardTracks/CareerRanks/careerRank1.json"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn season_calendar(client: &Client, spartan_token: &str) -> Result<SeasonCalendar> {
+    let res = client
+        .get(format!(
+            "{GAMECMS_PROGRESSION_URL}/Calendars/Seasons/SeasonCalendar.json"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn season_metadata(
+    client: &Client,
+    spartan_token: &str,
+    path: &str,
+) -> Result<SeasonMetadata> {
+    let res = client
+        .get(format!("{GAMECMS_PROGRESSION_URL}/{path}"))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+/// Customization other players can see, such as the equipped emblem and armor
+pub async fn customization(
+    client: &Client,
+    spartan_token: &str,
+    xuid: &str,
+) -> Result<PlayerCustomization> {
+    let res = client
+        .get(format!(
+            "https://economy.svc.halowaypoint.com/hi/players/xuid({xuid})/customization?view=public"
+        ))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+pub async fn emblem_mapping(client: &Client, spartan_token: &str) -> Result<EmblemMapping> {
+    let res = client
+        .get(format!("{GAMECMS_FILE_URL}/images/emblems/mapping.json"))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+/// Progression item definition, `path` being as referenced by customization
+pub async fn inventory_item(
+    client: &Client,
+    spartan_token: &str,
+    path: &str,
+) -> Result<InventoryItem> {
+    let res = client
+        .get(format!("{GAMECMS_PROGRESSION_URL}/{path}"))
+        .header("x-343-authorization-spartan", spartan_token)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+
+    decode(res).await
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fixed point in time moved by `minutes`, to lay histories out with.
+    pub(crate) fn at(minutes: i64) -> DateTime<Utc> {
+        use chrono::TimeZone;
+
+        Utc.with_ymd_and_hms(2023, 10, 1, 12, 0, 0).unwrap() + chrono::Duration::minutes(minutes)
+    }
+
+    /// History page, newest first, of matches started at the given minutes
+    /// and named after them.
+    pub(crate) fn page(minutes: &[i64]) -> Vec<Match> {
+        minutes
+            .iter()
+            .map(|x| test_match(&format!("m{x}"), at(*x)))
+            .collect()
+    }
+
+    /// Match history entry with only the fields history logic looks at set.
+    pub(crate) fn test_match(match_id: &str, start_time: DateTime<Utc>) -> Match {
+        let asset = serde_json::json!({ "AssetId": "", "AssetKind": 0, "VersionId": "" });
+
+        decode_value(&serde_json::json!({
+            "LastTeamId": 0,
+            "MatchId": match_id,
+            "MatchInfo": {
+                "ClearanceId": "",
+                "Duration": "PT10M",
+                "EndTime": start_time + chrono::Duration::minutes(10),
+                "GameVariantCategory": 0,
+                "GameplayInteraction": 0,
+                "LevelId": "",
+                "LifecycleMode": 0,
+                "MapVariant": asset,
+                "PlayableDuration": "PT10M",
+                "StartTime": start_time,
+                "TeamScoringEnabled": true,
+                "TeamsEnabled": true,
+                "UgcGameVariant": asset,
+            },
+            "Outcome": 2,
+            "PresentAtEndOfMatch": true,
+            "Rank": 1,
+        }))
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn history_len_finds_every_length() {
+        for len in (0..300usize).chain([1000, 4321, 10000]) {
+            let requests = AtomicUsize::new(0);
+            let page_len = |start: usize| {
+                requests.fetch_add(1, Ordering::Relaxed);
+                async move { Ok(len.saturating_sub(start).min(MATCHES_PAGE_SIZE)) }
+            };
+
+            assert_eq!(history_len(page_len).await.unwrap(), len);
+            // Doubling then bisecting, each logarithmic in the page count, plus the
+            // final page
+            assert!(
+                requests.into_inner() <= 2 * (len / MATCHES_PAGE_SIZE + 2).ilog2() as usize + 3
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn history_len_propagates_errors() {
+        let page_len = |_| async { Err::<usize, _>(async_graphql::Error::new("down")) };
+
+        assert!(history_len(page_len).await.is_err());
+    }
+
+    #[test]
+    fn defaults_missing_fields() {
+        let x: Match = decode_value(&serde_json::json!({
+            "MatchId": "abc",
+            "MatchInfo": { "StartTime": "2023-10-01T12:00:00Z" },
+        }))
+        .unwrap();
+
+        assert_eq!(x.match_id, "abc");
+        assert_eq!(x.outcome, 0);
+        assert_eq!(x.match_info.lifecycle_mode, 0);
+    }
+
+    #[test]
+    fn keeps_unmodelled_stat_blocks() {
+        let x: MatchStatsTeamStats = decode_value(&serde_json::json!({
+            "CoreStats": { "Kills": 3 },
+            "NewModeStats": { "Goals": 2 },
+        }))
+        .unwrap();
+
+        assert_eq!(x.core_stats.kills, 3);
+        assert_eq!(x.extra["NewModeStats"]["Goals"], 2);
+    }
+
+    #[test]
+    fn logs_each_decode_warning_once() {
+        let raw = serde_json::json!({ "Entries": [{ "OnlyInRaw": 1 }, { "OnlyInRaw": 2 }] });
+        let decoded = serde_json::json!({ "Entries": [{}, {}] });
+
+        log_decode_warnings("Once", &raw, &decoded);
+        log_decode_warnings("Once", &raw, &decoded);
+
+        let logged = LOGGED_DECODE_WARNINGS.lock().unwrap();
+        assert_eq!(logged.iter().filter(|x| x.contains("Once.")).count(), 1);
+        assert!(logged.contains("unexpected field Once.Entries[].OnlyInRaw"));
+    }
+
+    #[test]
+    fn decodes_upstream_names() {
+        let start_time = Utc::now();
+        let x = test_match("abc", start_time);
+
+        assert_eq!(x.match_id, "abc");
+        assert_eq!(x.match_info.start_time, start_time);
+        assert_eq!(x.match_info.duration.seconds(), 600.0);
+    }
 }
@@ -0,0 +1,513 @@
+use crate::enums::{GameVariantCategory, MatchType, Outcome};
+use crate::halo_requests;
+use crate::AuthData;
+use async_graphql::{InputObject, Result};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use std::future::Future;
+
+/// Upstream matches fetched per round while scanning with a filter.
+const SCAN_BATCH_SIZE: usize = 100;
+
+/// Upstream matches a single scan may look at before giving up.
+const MAX_SCANNED_MATCHES: usize = 2000;
+
+#[derive(InputObject, Default)]
+pub struct MatchFilter {
+    /// Upstream history type, defaults to all matches
+    #[graphql(name = "type")]
+    pub match_type: Option<MatchType>,
+    pub playlist_id: Option<String>,
+    pub map_id: Option<String>,
+    pub game_variant_category: Option<GameVariantCategory>,
+    pub outcome: Option<Outcome>,
+    /// Season id, only matches played during that season
+    pub season: Option<String>,
+    /// Only matches that started at or after this time
+    pub started_after: Option<DateTime<Utc>>,
+    /// Only matches that started before this time
+    pub started_before: Option<DateTime<Utc>>,
+}
+
+impl MatchFilter {
+    /// Narrows the start time bounds to `[start, end)`.
+    pub fn restrict_to(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) {
+        self.started_after = Some(self.started_after.map_or(start, |x| x.max(start)));
+        self.started_before = Some(self.started_before.map_or(end, |x| x.min(end)));
+    }
+
+    /// Whether every match returned by the upstream passes the filter.
+    pub fn is_empty(&self) -> bool {
+        self.playlist_id.is_none()
+            && self.map_id.is_none()
+            && self.game_variant_category.is_none()
+            && self.outcome.is_none()
+            && self.started_after.is_none()
+            && self.started_before.is_none()
+    }
+
+    pub fn matches(&self, x: &halo_requests::Match) -> bool {
+        let playlist_id = x.match_info.playlist.as_ref().map(|x| x.asset_id.as_str());
+
+        self.playlist_id
+            .as_ref()
+            .is_none_or(|id| playlist_id == Some(id.as_str()))
+            && self
+                .map_id
+                .as_ref()
+                .is_none_or(|id| &x.match_info.map_variant.asset_id == id)
+            && self.game_variant_category.is_none_or(|category| {
+                GameVariantCategory::from(x.match_info.game_variant_category) == category
+            })
+            && self
+                .outcome
+                .is_none_or(|outcome| Outcome::from(x.outcome) == outcome)
+            && self
+                .started_after
+                .is_none_or(|time| x.match_info.start_time >= time)
+            && self
+                .started_before
+                .is_none_or(|time| x.match_info.start_time < time)
+    }
+}
+
+pub struct ScanResult {
+    /// Matching entries with their upstream offsets, newest first
+    pub matches: Vec<(usize, halo_requests::Match)>,
+    /// Whether more matching entries may exist past the scanned range
+    pub has_more: bool,
+}
+
+/// Collects up to `count` filtered matches, walking the history from `start`
+/// towards older matches without passing `end`.
+pub async fn scan_forward(
+    data: &AuthData,
+    xuid: &str,
+    filter: &MatchFilter,
+    start: usize,
+    end: usize,
+    count: usize,
+) -> Result<ScanResult> {
+    scan_forward_with(fetcher(data, xuid, filter), filter, start, end, count).await
+}
+
+async fn scan_forward_with<F, Fut>(
+    fetch: F,
+    filter: &MatchFilter,
+    start: usize,
+    end: usize,
+    count: usize,
+) -> Result<ScanResult>
+where
+    F: Fn(usize, usize) -> Fut,
+    Fut: Future<Output = Result<Vec<halo_requests::Match>>>,
+{
+    let mut found = Vec::new();
+    let mut offset = start;
+
+    // Look for one extra match to learn whether there are more
+    while found.len() <= count && offset < end && offset - start < MAX_SCANNED_MATCHES {
+        let batch = if filter.is_empty() {
+            count + 1 - found.len()
+        } else {
+            SCAN_BATCH_SIZE
+        }
+        .min(end - offset);
+
+        let results = fetch(offset, batch).await?;
+        let len = results.len();
+
+        for (ind, x) in results.into_iter().enumerate() {
+            // History is newest first, so nothing past here can match
+            if filter
+                .started_after
+                .is_some_and(|time| x.match_info.start_time < time)
+            {
+                return Ok(finish(found, count));
+            }
+
+            if filter.matches(&x) {
+                found.push((offset + ind, x));
+            }
+        }
+
+        if len < batch {
+            return Ok(finish(found, count));
+        }
+
+        offset += batch;
+    }
+
+    let mut result = finish(found, count);
+    result.has_more |= offset < end && offset - start >= MAX_SCANNED_MATCHES;
+
+    Ok(result)
+}
+
+/// Collects up to `count` filtered matches, walking the history from `end`
+/// towards newer matches without passing `start`.
+pub async fn scan_backward(
+    data: &AuthData,
+    xuid: &str,
+    filter: &MatchFilter,
+    start: usize,
+    end: usize,
+    count: usize,
+) -> Result<ScanResult> {
+    scan_backward_with(fetcher(data, xuid, filter), filter, start, end, count).await
+}
+
+async fn scan_backward_with<F, Fut>(
+    fetch: F,
+    filter: &MatchFilter,
+    start: usize,
+    end: usize,
+    count: usize,
+) -> Result<ScanResult>
+where
+    F: Fn(usize, usize) -> Fut,
+    Fut: Future<Output = Result<Vec<halo_requests::Match>>>,
+{
+    let mut found = Vec::new();
+    let mut offset = end;
+
+    'scan: while found.len() <= count && offset > start && end - offset < MAX_SCANNED_MATCHES {
+        let batch = if filter.is_empty() {
+            count + 1 - found.len()
+        } else {
+            SCAN_BATCH_SIZE
+        }
+        .min(offset - start);
+        offset -= batch;
+
+        let results = fetch(offset, batch).await?;
+
+        for (ind, x) in results.into_iter().enumerate().rev() {
+            // History is newest first, so nothing before here can match
+            if filter
+                .started_before
+                .is_some_and(|time| x.match_info.start_time >= time)
+            {
+                break 'scan;
+            }
+
+            if filter.matches(&x) {
+                found.push((offset + ind, x));
+            }
+        }
+    }
+
+    let mut result = finish(found, count);
+    result.has_more |= offset > start && end - offset >= MAX_SCANNED_MATCHES;
+    result.matches.reverse();
+
+    Ok(result)
+}
+
+/// Fetches `count` matches of the history at an offset.
+fn fetcher<'a>(
+    data: &'a AuthData,
+    xuid: &'a str,
+    filter: &MatchFilter,
+) -> impl Fn(usize, usize) -> BoxFuture<'a, Result<Vec<halo_requests::Match>>> {
+    let match_type = filter.match_type;
+
+    move |offset, count| {
+        halo_requests::matches_range(
+            &data.client,
+            &data.spartan_token,
+            xuid,
+            match_type,
+            offset,
+            count,
+        )
+        .boxed()
+    }
+}
+
+fn finish(mut found: Vec<(usize, halo_requests::Match)>, count: usize) -> ScanResult {
+    let has_more = found.len() > count;
+    found.truncate(count);
+
+    ScanResult {
+        matches: found,
+        has_more,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::halo_requests::tests::{at, test_match};
+    use std::cell::Cell;
+
+    /// History of `len` matches ten minutes apart, newest first, with every
+    /// third one a loss and the rest wins.
+    fn history(len: usize) -> Vec<halo_requests::Match> {
+        (0..len)
+            .map(|ind| {
+                let mut x = test_match(&format!("m{ind}"), at(-10 * ind as i64));
+                x.outcome = if ind % 3 == 0 { 3 } else { 2 };
+                x
+            })
+            .collect()
+    }
+
+    fn losses() -> MatchFilter {
+        MatchFilter {
+            outcome: Some(Outcome::Loss),
+            ..Default::default()
+        }
+    }
+
+    fn offsets(result: &ScanResult) -> Vec<usize> {
+        result.matches.iter().map(|(offset, _)| *offset).collect()
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = MatchFilter::default();
+
+        assert!(filter.is_empty());
+        assert!(history(5).iter().all(|x| filter.matches(x)));
+    }
+
+    #[test]
+    fn filters_on_every_field() {
+        let mut x = test_match("m", at(0));
+        x.outcome = 2;
+        x.match_info.game_variant_category = 6;
+        x.match_info.map_variant.asset_id = "map".to_string();
+
+        let cases = [
+            (
+                MatchFilter {
+                    playlist_id: Some("playlist".to_string()),
+                    ..Default::default()
+                },
+                false,
+            ),
+            (
+                MatchFilter {
+                    map_id: Some("map".to_string()),
+                    ..Default::default()
+                },
+                true,
+            ),
+            (
+                MatchFilter {
+                    map_id: Some("other".to_string()),
+                    ..Default::default()
+                },
+                false,
+            ),
+            (
+                MatchFilter {
+                    game_variant_category: Some(GameVariantCategory::from(6)),
+                    ..Default::default()
+                },
+                true,
+            ),
+            (
+                MatchFilter {
+                    outcome: Some(Outcome::Win),
+                    ..Default::default()
+                },
+                true,
+            ),
+            (losses(), false),
+            (
+                MatchFilter {
+                    started_after: Some(at(0)),
+                    started_before: Some(at(1)),
+                    ..Default::default()
+                },
+                true,
+            ),
+            (
+                MatchFilter {
+                    started_before: Some(at(0)),
+                    ..Default::default()
+                },
+                false,
+            ),
+        ];
+
+        for (ind, (filter, expected)) in cases.iter().enumerate() {
+            assert!(!filter.is_empty());
+            assert_eq!(filter.matches(&x), *expected, "case {ind}");
+        }
+    }
+
+    #[test]
+    fn restricts_to_the_narrower_bounds() {
+        let mut filter = MatchFilter {
+            started_after: Some(at(10)),
+            ..Default::default()
+        };
+        filter.restrict_to(at(0), at(20));
+
+        assert_eq!(filter.started_after, Some(at(10)));
+        assert_eq!(filter.started_before, Some(at(20)));
+
+        filter.restrict_to(at(15), at(30));
+
+        assert_eq!(filter.started_after, Some(at(15)));
+        assert_eq!(filter.started_before, Some(at(20)));
+    }
+
+    /// Serves pages of `history`, counting the matches fetched.
+    fn fetch<'a>(
+        history: &'a [halo_requests::Match],
+        fetched: &'a Cell<usize>,
+    ) -> impl Fn(usize, usize) -> futures::future::Ready<Result<Vec<halo_requests::Match>>> + 'a
+    {
+        move |offset, count| {
+            let page =
+                history[offset.min(history.len())..(offset + count).min(history.len())].to_vec();
+            fetched.set(fetched.get() + page.len());
+
+            futures::future::ready(Ok(page))
+        }
+    }
+
+    #[actix_web::test]
+    async fn scans_forward_from_an_offset() {
+        let history = history(50);
+        let fetched = Cell::new(0);
+
+        let result = scan_forward_with(fetch(&history, &fetched), &losses(), 4, 50, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [6, 9, 12]);
+        assert!(result.has_more);
+
+        let result = scan_forward_with(fetch(&history, &fetched), &losses(), 40, 50, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [42, 45, 48]);
+        assert!(!result.has_more);
+    }
+
+    #[actix_web::test]
+    async fn scans_forward_without_passing_the_end() {
+        let history = history(50);
+        let fetched = Cell::new(0);
+
+        let result = scan_forward_with(fetch(&history, &fetched), &losses(), 0, 10, 5)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [0, 3, 6, 9]);
+        assert!(!result.has_more);
+        assert_eq!(fetched.get(), 10);
+    }
+
+    #[actix_web::test]
+    async fn scans_forward_only_as_far_as_needed_without_a_filter() {
+        let history = history(500);
+        let fetched = Cell::new(0);
+
+        let result = scan_forward_with(
+            fetch(&history, &fetched),
+            &MatchFilter::default(),
+            10,
+            500,
+            5,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(offsets(&result), [10, 11, 12, 13, 14]);
+        assert!(result.has_more);
+        assert_eq!(fetched.get(), 6);
+    }
+
+    #[actix_web::test]
+    async fn stops_scanning_forward_at_older_matches() {
+        let history = history(500);
+        let fetched = Cell::new(0);
+        let filter = MatchFilter {
+            started_after: Some(at(-45)),
+            ..losses()
+        };
+
+        let result = scan_forward_with(fetch(&history, &fetched), &filter, 0, 500, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [0, 3]);
+        assert!(!result.has_more);
+    }
+
+    #[actix_web::test]
+    async fn gives_up_forward_scans_after_too_many_matches() {
+        let history = history(MAX_SCANNED_MATCHES + 500);
+        let fetched = Cell::new(0);
+        let filter = MatchFilter {
+            playlist_id: Some("nowhere".to_string()),
+            ..Default::default()
+        };
+
+        let result = scan_forward_with(fetch(&history, &fetched), &filter, 0, history.len(), 10)
+            .await
+            .unwrap();
+
+        assert!(result.matches.is_empty());
+        assert!(result.has_more);
+        assert_eq!(fetched.get(), MAX_SCANNED_MATCHES);
+    }
+
+    #[actix_web::test]
+    async fn scans_backward_from_an_offset() {
+        let history = history(50);
+        let fetched = Cell::new(0);
+
+        let result = scan_backward_with(fetch(&history, &fetched), &losses(), 0, 20, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [12, 15, 18]);
+        assert!(result.has_more);
+
+        let result = scan_backward_with(fetch(&history, &fetched), &losses(), 0, 8, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [0, 3, 6]);
+        assert!(!result.has_more);
+    }
+
+    #[actix_web::test]
+    async fn stops_scanning_backward_at_newer_matches() {
+        let history = history(500);
+        let fetched = Cell::new(0);
+        let filter = MatchFilter {
+            started_before: Some(at(-100)),
+            ..losses()
+        };
+
+        let result = scan_backward_with(fetch(&history, &fetched), &filter, 0, 20, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(offsets(&result), [12, 15, 18]);
+        assert!(!result.has_more);
+    }
+
+    #[actix_web::test]
+    async fn propagates_fetch_errors() {
+        let fetch = |_, _| {
+            futures::future::ready(Err::<Vec<halo_requests::Match>, _>(
+                async_graphql::Error::new("down"),
+            ))
+        };
+
+        assert!(scan_forward_with(fetch, &losses(), 0, 50, 5).await.is_err());
+        assert!(scan_backward_with(fetch, &losses(), 0, 50, 5)
+            .await
+            .is_err());
+    }
+}
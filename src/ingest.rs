@@ -0,0 +1,233 @@
+use crate::auth::{self, SpartanTokenResponse};
+use crate::enums::LifecycleMode;
+use crate::halo_requests;
+use crate::store::{Enrollment, Store, SyncProgress};
+use async_graphql::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// History pages the backfill walks per player in each round, so one long
+/// history doesn't hold up everyone else.
+const BACKFILL_PAGES_PER_ROUND: usize = 4;
+
+/// How long before it expires a Spartan token is renewed, so it doesn't run
+/// out in the middle of a round.
+const TOKEN_RENEWAL_MARGIN: chrono::Duration = chrono::Duration::minutes(10);
+
+/// Archives the match history of enrolled players, polling for new matches
+/// every `interval` and backfilling older ones a few pages at a time.
+///
+/// Each player is synced with a Spartan token signed in with their stored
+/// refresh token. Spartan tokens are only kept in memory.
+pub async fn run(client: Client, store: Arc<Store>, interval: Duration) {
+    let mut tokens = HashMap::new();
+
+    loop {
+        let enrollments = store.enrollments().await.unwrap_or_else(|err| {
+            println!("Failed to read enrollments: {}", err.message);
+            Vec::new()
+        });
+
+        for enrollment in enrollments.iter().filter(|x| !x.credential_expired) {
+            let progress = sync(&client, &store, &mut tokens, enrollment).await;
+
+            if let Some(error) = &progress.error {
+                println!("Failed to sync {}: {error}", enrollment.gamertag);
+            }
+
+            if let Err(err) = store.record_sync(&enrollment.xuid, &progress).await {
+                println!(
+                    "Failed to record sync of {}: {}",
+                    enrollment.gamertag, err.message
+                );
+            }
+        }
+
+        actix_web::rt::time::sleep(interval).await;
+    }
+}
+
+async fn sync(
+    client: &Client,
+    store: &Store,
+    tokens: &mut HashMap<String, SpartanTokenResponse>,
+    enrollment: &Enrollment,
+) -> SyncProgress {
+    let mut progress = SyncProgress {
+        backfill_offset: enrollment.backfill_offset,
+        backfill_complete: enrollment.backfill_complete,
+        newest_start_time: enrollment.newest_start_time,
+        ingested: 0,
+        error: None,
+    };
+
+    let result = match spartan_token(client, store, tokens, enrollment).await {
+        Ok(token) => sync_round(client, store, &token, enrollment, &mut progress).await,
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        progress.error = Some(err.message);
+    }
+
+    progress
+}
+
+/// Spartan token to sync a player with, signing in again with their refresh
+/// token when the last one is about to expire.
+async fn spartan_token(
+    client: &Client,
+    store: &Store,
+    tokens: &mut HashMap<String, SpartanTokenResponse>,
+    enrollment: &Enrollment,
+) -> Result<String> {
+    if let Some(token) = tokens.get(&enrollment.xuid) {
+        if token.expires_utc.iso8601_date - TOKEN_RENEWAL_MARGIN > Utc::now() {
+            return Ok(token.spartan_token.clone());
+        }
+    }
+
+    let refresh_token = store.refresh_token(enrollment)?;
+
+    let Some(sign_in) = auth::refresh(client, &refresh_token).await? else {
+        tokens.remove(&enrollment.xuid);
+        store.expire_credential(&enrollment.xuid).await?;
+
+        return Err(async_graphql::Error::new(
+            "Refresh token was turned down, enroll the player again",
+        ));
+    };
+
+    store
+        .renew_refresh_token(&enrollment.xuid, &sign_in.refresh_token)
+        .await?;
+
+    let token = sign_in.spartan_token.spartan_token.clone();
+    tokens.insert(enrollment.xuid.clone(), sign_in.spartan_token);
+
+    Ok(token)
+}
+
+async fn sync_round(
+    client: &Client,
+    store: &Store,
+    token: &str,
+    enrollment: &Enrollment,
+    progress: &mut SyncProgress,
+) -> Result<()> {
+    // Before the first backfill round there is nothing to poll up to, unless
+    // the history was empty
+    if progress.newest_start_time.is_some() || progress.backfill_complete {
+        let watermark = progress
+            .newest_start_time
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let mut newest = None;
+        let mut newer = 0;
+
+        'poll: loop {
+            let page = history_page(client, token, &enrollment.xuid, newer).await?;
+
+            for x in &page {
+                if x.match_info.start_time <= watermark {
+                    break 'poll;
+                }
+
+                if !store.has_match(&x.match_id).await? {
+                    ingest(client, store, token, &x.match_id).await?;
+                    progress.ingested += 1;
+                }
+
+                newest = newest.or(Some(x.match_info.start_time));
+                newer += 1;
+            }
+
+            if page.len() < halo_requests::MATCHES_PAGE_SIZE {
+                break;
+            }
+        }
+
+        // New matches push older ones further down the history
+        progress.backfill_offset += newer;
+        progress.newest_start_time = newest.or(progress.newest_start_time);
+    }
+
+    for _ in 0..BACKFILL_PAGES_PER_ROUND {
+        if progress.backfill_complete {
+            break;
+        }
+
+        let page = history_page(client, token, &enrollment.xuid, progress.backfill_offset).await?;
+
+        if progress.backfill_offset == 0 {
+            progress.newest_start_time = page.first().map(|x| x.match_info.start_time);
+        }
+
+        for x in &page {
+            if !store.has_match(&x.match_id).await? {
+                ingest(client, store, token, &x.match_id).await?;
+                progress.ingested += 1;
+            }
+
+            progress.backfill_offset += 1;
+        }
+
+        progress.backfill_complete = page.len() < halo_requests::MATCHES_PAGE_SIZE;
+    }
+
+    Ok(())
+}
+
+async fn history_page(
+    client: &Client,
+    spartan_token: &str,
+    xuid: &str,
+    start: usize,
+) -> Result<Vec<halo_requests::Match>> {
+    halo_requests::matches(
+        client,
+        spartan_token,
+        xuid,
+        None,
+        Some(start),
+        Some(halo_requests::MATCHES_PAGE_SIZE),
+    )
+    .await
+    .map(|x| x.results)
+}
+
+/// Archives the stats of a match along with the skill results of its human
+/// players. The stats go last, so an archived match is always complete.
+async fn ingest(client: &Client, store: &Store, spartan_token: &str, match_id: &str) -> Result<()> {
+    let (stats, raw) = halo_requests::stats(client, spartan_token, match_id).await?;
+
+    let players: Vec<String> = stats
+        .players
+        .iter()
+        .filter(|x| !x.is_bot())
+        .map(|x| {
+            x.player_id
+                .trim_start_matches("xuid(")
+                .trim_end_matches(')')
+                .to_string()
+        })
+        .collect();
+
+    if LifecycleMode::from(stats.match_info.lifecycle_mode) == LifecycleMode::Matchmade
+        && !players.is_empty()
+    {
+        let skills = halo_requests::skill(client, spartan_token, match_id, &players).await?;
+
+        for (skill, raw) in skills.iter().filter(|(x, _)| x.result_code == 0) {
+            let player_id = skill.id.trim_start_matches("xuid(").trim_end_matches(')');
+
+            store.save_skill(match_id, player_id, skill, raw).await?;
+        }
+    }
+
+    store.save_stats(&stats, &raw).await?;
+
+    Ok(())
+}
@@ -3,36 +3,108 @@ use async_graphql::dataloader::*;
 use async_graphql::types::connection::*;
 use async_graphql::OutputType;
 use async_graphql::{
-    http::GraphiQLSource, ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Result,
-    Schema, SimpleObject,
+    http::GraphiQLSource, ComplexObject, Context, Interface, Object, Result, Schema, SimpleObject,
+    Subscription, Union, ID,
 };
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use cache::{LruCache, TtlCache};
+use chrono::{DateTime, Utc};
+use cursor::MatchCursor;
 use dotenv::dotenv;
-use futures::StreamExt;
+use enums::{
+    AssetKind, BotDifficulty, BotType, CareerRankTier, GameVariantCategory, GameplayInteraction,
+    LifecycleMode, MatchType, Outcome, PlayerType,
+};
+use futures::{Stream, StreamExt, TryStreamExt};
+use history::MatchFilter;
+use node::NodeId;
 use reqwest::Client;
-use serde_json::Value;
+use scalars::Duration;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+use store::{CredentialKey, Enrollment, Store};
+use tokio::sync::OnceCell;
+use watch::MatchWatcher;
 
 mod auth;
+mod cache;
+mod cursor;
+mod enums;
 mod halo_requests;
+mod history;
+mod ingest;
+mod node;
+mod scalars;
+mod store;
+mod watch;
 
 struct Query;
 
+struct Mutation;
+
+struct Subscription;
+
 #[derive(SimpleObject)]
 struct SpartanToken {
     token: String,
-    expires_at: String,
+    expires_at: DateTime<Utc>,
     refresh_token: String,
 }
 
+/// Object that can be refetched with `Query.node` by its global id
+#[derive(Interface)]
+#[graphql(field(name = "id", ty = "ID"))]
+enum Node {
+    Player(Player),
+    Match(Box<Match>),
+    Team(Team),
+}
+
+impl Node {
+    /// Match or team node identified by `id`, out of the match's stats
+    fn from_stats(id: NodeId, res: halo_requests::MatchStats) -> Option<Node> {
+        let info = res.match_info.clone();
+        let m = Match::new(res.match_id.clone(), info);
+
+        match id {
+            NodeId::Player { .. } => None,
+            NodeId::Match { .. } => Some(Node::Match(Box::new(m))),
+            NodeId::Team { team_id, .. } => m
+                .team_connection(res)
+                .edges
+                .into_iter()
+                .map(|x| x.node)
+                .find(|x| x.team_id == team_id)
+                .map(Node::Team),
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 #[graphql(complex)]
 struct Player {
-    id: String,
+    xuid: String,
     gamertag: String,
     pic: PlayerPic,
 }
 
+impl From<halo_requests::Gamer> for Player {
+    fn from(x: halo_requests::Gamer) -> Self {
+        Player {
+            xuid: x.xuid,
+            gamertag: x.gamertag,
+            pic: PlayerPic {
+                small: x.gamerpic.small,
+                medium: x.gamerpic.medium,
+                large: x.gamerpic.large,
+                xlarge: x.gamerpic.xlarge,
+            },
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 struct PlayerPic {
     small: String,
@@ -42,45 +114,183 @@ struct PlayerPic {
 }
 
 #[derive(SimpleObject)]
+struct Appearance {
+    service_tag: String,
+    emblem: Emblem,
+    backdrop: Option<InventoryItem>,
+    player_title: Option<InventoryItem>,
+    /// Equipped armor core, falling back to the first one
+    armor_core: Option<ArmorCore>,
+}
+
+#[derive(SimpleObject)]
+struct Emblem {
+    item: InventoryItem,
+    configuration_id: i64,
+    /// Emblem image in the equipped colors
+    image_url: Option<String>,
+    /// Nameplate image matching the emblem colors
+    nameplate_image_url: Option<String>,
+    /// Color of text drawn over the nameplate
+    text_color: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct ArmorCore {
+    core_id: String,
+    core_type: String,
+    /// Equipped theme, falling back to the core's default
+    theme: Option<InventoryItem>,
+}
+
+/// Equipped core out of a player's armor cores, or the first one if none is
+/// marked as equipped.
+fn equipped_core(cores: Vec<halo_requests::ArmorCore>) -> Option<halo_requests::ArmorCore> {
+    let ind = cores.iter().position(|x| x.is_equipped).unwrap_or(0);
+
+    cores.into_iter().nth(ind)
+}
+
+/// Progression item such as an emblem or armor theme
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct InventoryItem {
+    path: String,
+}
+
+impl InventoryItem {
+    /// `None` for the empty paths upstream uses for unset slots
+    fn new(path: String) -> Option<Self> {
+        (!path.is_empty()).then_some(InventoryItem { path })
+    }
+}
+
+struct Progression {
+    player_id: String,
+}
+
+#[derive(SimpleObject)]
+struct CareerRank {
+    rank: i32,
+    title: String,
+    subtitle: String,
+    tier: CareerRankTier,
+    grade: i32,
+    /// XP earned towards the next rank
+    xp: i32,
+    /// XP the next rank takes in total
+    next_rank_xp: Option<i32>,
+    xp_to_next_rank: Option<i32>,
+    has_reached_max_rank: bool,
+    icon_url: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct RewardTrack {
+    path: String,
+    track_type: String,
+    rank: i32,
+    /// XP earned towards the next rank
+    xp: i32,
+    is_owned: bool,
+    has_reached_max_rank: bool,
+    /// Whether this is the currently active operation
+    active: bool,
+}
+
+#[derive(SimpleObject, Clone)]
+#[graphql(complex)]
 struct AssetReference {
     asset_id: String,
-    asset_kind: i32,
+    asset_kind: AssetKind,
+    /// Raw `asset_kind` value
+    asset_kind_value: i32,
     version_id: String,
 }
 
+impl From<halo_requests::AssetReference> for AssetReference {
+    fn from(x: halo_requests::AssetReference) -> Self {
+        AssetReference {
+            asset_id: x.asset_id,
+            asset_kind: x.asset_kind.into(),
+            asset_kind_value: x.asset_kind,
+            version_id: x.version_id,
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 #[graphql(complex)]
 struct Match {
-    id: String,
+    match_id: String,
     clearance_id: String,
-    duration: String,
-    end_time: String,
-    game_variant_category: i32,
-    gameplay_interaction: i32,
+    duration: Duration,
+    duration_seconds: f64,
+    end_time: DateTime<Utc>,
+    game_variant_category: GameVariantCategory,
+    /// Raw `game_variant_category` value
+    game_variant_category_value: i32,
+    gameplay_interaction: GameplayInteraction,
+    /// Raw `gameplay_interaction` value
+    gameplay_interaction_value: i32,
     level_id: String,
-    lifecycle_mode: i32,
+    lifecycle_mode: LifecycleMode,
+    /// Raw `lifecycle_mode` value
+    lifecycle_mode_value: i32,
     map_variant: AssetReference,
-    playable_duration: String,
-    playlist: Option<Value>,
+    playable_duration: Duration,
+    playable_duration_seconds: f64,
+    playlist: Option<AssetReference>,
     playlist_experience: Option<Value>,
-    playlist_map_mode_pair: Option<Value>,
+    playlist_map_mode_pair: Option<AssetReference>,
     season_id: Option<Value>,
-    start_time: String,
+    start_time: DateTime<Utc>,
     team_scoring_enabled: bool,
     teams_enabled: bool,
     ugc_game_variant: AssetReference,
+    /// Upstream fields not modelled by this API
+    extra: Value,
+}
+
+/// Lobby settings of a custom game
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct CustomGame {
+    #[graphql(skip)]
+    match_id: String,
+    map: AssetReference,
+    game_variant: AssetReference,
+    teams_enabled: bool,
+    team_scoring_enabled: bool,
+}
+
+#[derive(SimpleObject)]
+struct LobbyMember {
+    player_id: String,
+    /// Team the player finished on
+    team_id: i32,
+    is_bot: bool,
+    present_at_beginning: bool,
+    present_at_completion: bool,
+    joined_in_progress: bool,
+    left_in_progress: bool,
 }
 
 #[derive(SimpleObject)]
 struct MatchEdgeData {
     last_team_id: i32,
-    outcome: i32,
+    outcome: Outcome,
+    /// Raw `outcome` value
+    outcome_value: i32,
     present_at_end_of_match: bool,
     rank: i32,
 }
 
 #[derive(SimpleObject)]
+#[graphql(complex)]
 struct Team {
+    #[graphql(skip)]
+    match_id: String,
     team_id: i32,
     rank: i32,
     players: Connection<
@@ -95,9 +305,36 @@ struct Team {
 
 #[derive(SimpleObject)]
 struct TeamEdgeData {
-    outcome: i32,
-    score: i32,
+    outcome: Outcome,
+    /// Raw `outcome` value
+    outcome_value: i32,
+    #[graphql(deprecation = "Use `personalScore`")]
     total_personal_score: i32,
+    #[graphql(flatten)]
+    core_stats: CoreStats,
+    stronghold_stats: Option<StrongholdStats>,
+    mode_stats: Option<ModeStats>,
+    /// Stat blocks of modes not modelled by this API
+    extra_mode_stats: Value,
+}
+
+#[derive(SimpleObject)]
+struct ServiceRecord {
+    time_played: Duration,
+    time_played_seconds: f64,
+    matches_completed: i32,
+    wins: i32,
+    losses: i32,
+    ties: i32,
+    #[graphql(flatten)]
+    core_stats: CoreStats,
+    stronghold_stats: Option<StrongholdStats>,
+}
+
+#[derive(SimpleObject)]
+struct CoreStats {
+    score: i32,
+    personal_score: i32,
     rounds_won: i32,
     rounds_lost: i32,
     rounds_tied: i32,
@@ -107,7 +344,8 @@ struct TeamEdgeData {
     kda: f32,
     suicides: i32,
     betrayals: i32,
-    average_life_duration: String,
+    average_life_duration: Duration,
+    average_life_duration_seconds: f64,
     grenade_kills: i32,
     headshot_kills: i32,
     melee_kills: i32,
@@ -129,7 +367,54 @@ struct TeamEdgeData {
     deprecated_damage_taken: f32,
     spawns: i32,
     objectives_completed: i32,
-    stronghold_stats: Option<StrongholdStats>,
+    /// Upstream fields not modelled by this API
+    extra: Value,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsCoreStats> for CoreStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsCoreStats) -> Self {
+        CoreStats {
+            score: x.score,
+            personal_score: x.personal_score,
+            rounds_won: x.rounds_won,
+            rounds_lost: x.rounds_lost,
+            rounds_tied: x.rounds_tied,
+            kills: x.kills,
+            deaths: x.deaths,
+            assists: x.assists,
+            kda: x.kda,
+            suicides: x.suicides,
+            betrayals: x.betrayals,
+            average_life_duration_seconds: x.average_life_duration.seconds(),
+            average_life_duration: x.average_life_duration,
+            grenade_kills: x.grenade_kills,
+            headshot_kills: x.headshot_kills,
+            melee_kills: x.melee_kills,
+            power_weapon_kills: x.power_weapon_kills,
+            shots_fired: x.shots_fired,
+            shots_hit: x.shots_hit,
+            accuracy: x.accuracy,
+            damage_dealt: x.damage_dealt,
+            damage_taken: x.damage_taken,
+            callout_assists: x.callout_assists,
+            vehicle_destroys: x.vehicle_destroys,
+            driver_assists: x.driver_assists,
+            hijacks: x.hijacks,
+            emp_assists: x.emp_assists,
+            max_killing_spree: x.max_killing_spree,
+            medals: x.medals.into_iter().map(ScoreChange::from).collect(),
+            personal_scores: x
+                .personal_scores
+                .into_iter()
+                .map(ScoreChange::from)
+                .collect(),
+            deprecated_damage_dealt: x.deprecated_damage_dealt,
+            deprecated_damage_taken: x.deprecated_damage_taken,
+            spawns: x.spawns,
+            objectives_completed: x.objectives_completed,
+            extra: Value::Object(x.extra),
+        }
+    }
 }
 
 #[derive(SimpleObject)]
@@ -138,28 +423,128 @@ struct StrongholdStats {
     defensive_kills: i32,
     offensive_kills: i32,
     secures: i32,
-    occupation_time: String,
+    occupation_time: Duration,
+    occupation_time_seconds: f64,
     scoring_ticks: i32,
 }
 
+impl From<halo_requests::MatchStatsTeamStatsZonesStats> for StrongholdStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsZonesStats) -> Self {
+        StrongholdStats {
+            captures: x.stronghold_captures,
+            defensive_kills: x.stronghold_defensive_kills,
+            offensive_kills: x.stronghold_offensive_kills,
+            secures: x.stronghold_secures,
+            occupation_time_seconds: x.stronghold_occupation_time.seconds(),
+            occupation_time: x.stronghold_occupation_time,
+            scoring_ticks: x.stronghold_scoring_ticks,
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 #[graphql(complex)]
 struct PlayerEdgeData {
     match_id: String,
     player_id: String,
-    player_type: i32,
-    bot_attributes: Option<Value>,
+    player_type: PlayerType,
+    /// Raw `player_type` value
+    player_type_value: i32,
+    is_bot: bool,
+    bot_attributes: Option<BotAttributes>,
     last_team_id: i32,
-    outcome: i32,
+    outcome: Outcome,
+    /// Raw `outcome` value
+    outcome_value: i32,
     rank: i32,
-    first_joined_time: String,
-    last_leave_time: Option<String>,
+    first_joined_time: DateTime<Utc>,
+    last_leave_time: Option<DateTime<Utc>>,
     present_at_beginning: bool,
     joined_in_progress: bool,
     left_in_progress: bool,
     present_at_completion: bool,
-    time_played: String,
-    confirmed_participation: Option<Value>,
+    time_played: Duration,
+    time_played_seconds: f64,
+    confirmed_participation: Option<ConfirmedParticipation>,
+    /// Upstream fields not modelled by this API
+    extra: Value,
+}
+
+#[derive(SimpleObject)]
+struct BotAttributes {
+    difficulty: BotDifficulty,
+    /// Raw `difficulty` value
+    difficulty_value: i32,
+    bot_type: Option<BotType>,
+    /// Raw `bot_type` value
+    bot_type_value: Option<i32>,
+    /// Upstream fields not modelled by this API
+    extra: Value,
+}
+
+impl From<halo_requests::BotAttributes> for BotAttributes {
+    fn from(x: halo_requests::BotAttributes) -> Self {
+        BotAttributes {
+            difficulty: x.difficulty.into(),
+            difficulty_value: x.difficulty,
+            bot_type: x.bot_type.map(BotType::from),
+            bot_type_value: x.bot_type,
+            extra: Value::Object(x.extra),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct ConfirmedParticipation {
+    confirmed: bool,
+    /// Upstream fields sent with the confirmation, if it came as an object
+    extra: Value,
+}
+
+impl From<halo_requests::ConfirmedParticipation> for ConfirmedParticipation {
+    fn from(x: halo_requests::ConfirmedParticipation) -> Self {
+        match x {
+            halo_requests::ConfirmedParticipation::Flag(confirmed) => ConfirmedParticipation {
+                confirmed,
+                extra: Value::Object(Map::new()),
+            },
+            halo_requests::ConfirmedParticipation::Details(details) => ConfirmedParticipation {
+                confirmed: details.confirmed.unwrap_or(true),
+                extra: Value::Object(details.extra),
+            },
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct TeamMmr {
+    team_id: i32,
+    mmr: f32,
+}
+
+#[derive(SimpleObject)]
+struct Counterfactual {
+    kills: f32,
+    deaths: f32,
+}
+
+impl From<halo_requests::SkillResultCounterfactualsKillsDeaths> for Counterfactual {
+    fn from(x: halo_requests::SkillResultCounterfactualsKillsDeaths) -> Self {
+        Counterfactual {
+            kills: x.kills,
+            deaths: x.deaths,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct TierCounterfactuals {
+    bronze: Counterfactual,
+    silver: Counterfactual,
+    gold: Counterfactual,
+    platinum: Counterfactual,
+    diamond: Counterfactual,
+    onyx: Counterfactual,
 }
 
 #[derive(SimpleObject)]
@@ -175,43 +560,326 @@ struct Csr {
     initial_measurement_matches: i32,
 }
 
+impl From<halo_requests::SkillResultRankRecapCsr> for Csr {
+    fn from(x: halo_requests::SkillResultRankRecapCsr) -> Self {
+        Csr {
+            value: x.value,
+            measurement_matches_remaining: x.measurement_matches_remaining,
+            tier: x.tier,
+            tier_start: x.tier_start,
+            sub_tier: x.sub_tier,
+            next_tier: x.next_tier,
+            next_tier_start: x.next_tier_start,
+            next_sub_tier: x.next_sub_tier,
+            initial_measurement_matches: x.initial_measurement_matches,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct PlaylistCsr {
+    current: Csr,
+    season_max: Csr,
+    all_time_max: Csr,
+}
+
 #[derive(SimpleObject)]
 struct TeamPlayerEdgeData {
     player_id: String,
-    score: i32,
-    personal_score: i32,
-    rounds_won: i32,
-    rounds_lost: i32,
-    rounds_tied: i32,
-    kills: i32,
-    deaths: i32,
-    assists: i32,
-    kda: f32,
-    suicides: i32,
-    betrayals: i32,
-    average_life_duration: String,
-    grenade_kills: i32,
-    headshot_kills: i32,
-    melee_kills: i32,
-    power_weapon_kills: i32,
-    shots_fired: i32,
-    shots_hit: i32,
-    accuracy: f32,
-    damage_dealt: i32,
-    damage_taken: i32,
-    callout_assists: i32,
-    vehicle_destroys: i32,
-    driver_assists: i32,
-    hijacks: i32,
-    emp_assists: i32,
-    max_killing_spree: i32,
-    medals: Vec<ScoreChange>,
-    personal_scores: Vec<ScoreChange>,
-    deprecated_damage_dealt: f32,
-    deprecated_damage_taken: f32,
-    spawns: i32,
-    objectives_completed: i32,
+    is_bot: bool,
+    #[graphql(flatten)]
+    core_stats: CoreStats,
     stronghold_stats: Option<StrongholdStats>,
+    mode_stats: Option<ModeStats>,
+    /// Stat blocks of modes not modelled by this API
+    extra_mode_stats: Value,
+}
+
+/// Stats for the objective of the match's game variant
+#[derive(Union)]
+enum ModeStats {
+    CaptureTheFlag(CaptureTheFlagStats),
+    Oddball(OddballStats),
+    Strongholds(StrongholdStats),
+    Zone(ZoneStats),
+    Elimination(EliminationStats),
+    Extraction(ExtractionStats),
+    Infection(InfectionStats),
+    Stockpile(StockpileStats),
+}
+
+impl ModeStats {
+    /// Picks the block matching `category`, falling back to whichever block
+    /// is present for categories without a dedicated one.
+    fn new(
+        category: GameVariantCategory,
+        stats: &halo_requests::MatchStatsTeamStats,
+    ) -> Option<ModeStats> {
+        let ctf = || {
+            stats
+                .capture_the_flag_stats
+                .clone()
+                .map(|x| ModeStats::CaptureTheFlag(x.into()))
+        };
+        let oddball = || {
+            stats
+                .oddball_stats
+                .clone()
+                .map(|x| ModeStats::Oddball(x.into()))
+        };
+        let strongholds = || {
+            stats
+                .zones_stats
+                .clone()
+                .map(|x| ModeStats::Strongholds(x.into()))
+        };
+        let zone = || stats.zones_stats.clone().map(|x| ModeStats::Zone(x.into()));
+        let elimination = || {
+            stats
+                .elimination_stats
+                .clone()
+                .map(|x| ModeStats::Elimination(x.into()))
+        };
+        let extraction = || {
+            stats
+                .extraction_stats
+                .clone()
+                .map(|x| ModeStats::Extraction(x.into()))
+        };
+        let infection = || {
+            stats
+                .infection_stats
+                .clone()
+                .map(|x| ModeStats::Infection(x.into()))
+        };
+        let stockpile = || {
+            stats
+                .stockpile_stats
+                .clone()
+                .map(|x| ModeStats::Stockpile(x.into()))
+        };
+
+        match category {
+            GameVariantCategory::Ctf => ctf(),
+            GameVariantCategory::Oddball => oddball(),
+            GameVariantCategory::Strongholds => strongholds(),
+            GameVariantCategory::KingOfTheHill
+            | GameVariantCategory::TotalControl
+            | GameVariantCategory::LandGrab => zone(),
+            GameVariantCategory::Elimination => elimination(),
+            GameVariantCategory::Extraction => extraction(),
+            GameVariantCategory::Infection => infection(),
+            GameVariantCategory::Stockpile => stockpile(),
+            _ => ctf()
+                .or_else(oddball)
+                .or_else(strongholds)
+                .or_else(elimination)
+                .or_else(extraction)
+                .or_else(infection)
+                .or_else(stockpile),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct ZoneStats {
+    captures: i32,
+    defensive_kills: i32,
+    offensive_kills: i32,
+    secures: i32,
+    occupation_time: Duration,
+    occupation_time_seconds: f64,
+    scoring_ticks: i32,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsZonesStats> for ZoneStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsZonesStats) -> Self {
+        ZoneStats {
+            captures: x.zone_captures,
+            defensive_kills: x.zone_defensive_kills,
+            offensive_kills: x.zone_offensive_kills,
+            secures: x.zone_secures,
+            occupation_time_seconds: x.zone_occupation_time.seconds(),
+            occupation_time: x.zone_occupation_time,
+            scoring_ticks: x.zone_scoring_ticks,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct CaptureTheFlagStats {
+    flag_capture_assists: i32,
+    flag_captures: i32,
+    flag_carriers_killed: i32,
+    flag_grabs: i32,
+    flag_returners_killed: i32,
+    flag_returns: i32,
+    flag_secures: i32,
+    flag_steals: i32,
+    kills_as_flag_carrier: i32,
+    kills_as_flag_returner: i32,
+    time_as_flag_carrier: Duration,
+    time_as_flag_carrier_seconds: f64,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsCaptureTheFlagStats> for CaptureTheFlagStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsCaptureTheFlagStats) -> Self {
+        CaptureTheFlagStats {
+            flag_capture_assists: x.flag_capture_assists,
+            flag_captures: x.flag_captures,
+            flag_carriers_killed: x.flag_carriers_killed,
+            flag_grabs: x.flag_grabs,
+            flag_returners_killed: x.flag_returners_killed,
+            flag_returns: x.flag_returns,
+            flag_secures: x.flag_secures,
+            flag_steals: x.flag_steals,
+            kills_as_flag_carrier: x.kills_as_flag_carrier,
+            kills_as_flag_returner: x.kills_as_flag_returner,
+            time_as_flag_carrier_seconds: x.time_as_flag_carrier.seconds(),
+            time_as_flag_carrier: x.time_as_flag_carrier,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct OddballStats {
+    kills_as_skull_carrier: i32,
+    longest_time_as_skull_carrier: Duration,
+    longest_time_as_skull_carrier_seconds: f64,
+    skull_carriers_killed: i32,
+    skull_grabs: i32,
+    time_as_skull_carrier: Duration,
+    time_as_skull_carrier_seconds: f64,
+    skull_scoring_ticks: i32,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsOddballStats> for OddballStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsOddballStats) -> Self {
+        OddballStats {
+            kills_as_skull_carrier: x.kills_as_skull_carrier,
+            longest_time_as_skull_carrier_seconds: x.longest_time_as_skull_carrier.seconds(),
+            longest_time_as_skull_carrier: x.longest_time_as_skull_carrier,
+            skull_carriers_killed: x.skull_carriers_killed,
+            skull_grabs: x.skull_grabs,
+            time_as_skull_carrier_seconds: x.time_as_skull_carrier.seconds(),
+            time_as_skull_carrier: x.time_as_skull_carrier,
+            skull_scoring_ticks: x.skull_scoring_ticks,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct EliminationStats {
+    allies_revived: i32,
+    elimination_assists: i32,
+    eliminations: i32,
+    enemy_revives_denied: i32,
+    executions: i32,
+    killed_in_order: i32,
+    last_player_standing_kills: i32,
+    rounds_survived: i32,
+    times_revived_by_ally: i32,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsEliminationStats> for EliminationStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsEliminationStats) -> Self {
+        EliminationStats {
+            allies_revived: x.allies_revived,
+            elimination_assists: x.elimination_assists,
+            eliminations: x.eliminations,
+            enemy_revives_denied: x.enemy_revives_denied,
+            executions: x.executions,
+            killed_in_order: x.killed_in_order,
+            last_player_standing_kills: x.last_player_standing_kills,
+            rounds_survived: x.rounds_survived,
+            times_revived_by_ally: x.times_revived_by_ally,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct ExtractionStats {
+    successful_extractions: i32,
+    extraction_conversions_denied: i32,
+    extraction_conversions_completed: i32,
+    extraction_initiations_denied: i32,
+    extraction_initiations_completed: i32,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsExtractionStats> for ExtractionStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsExtractionStats) -> Self {
+        ExtractionStats {
+            successful_extractions: x.successful_extractions,
+            extraction_conversions_denied: x.extraction_conversions_denied,
+            extraction_conversions_completed: x.extraction_conversions_completed,
+            extraction_initiations_denied: x.extraction_initiations_denied,
+            extraction_initiations_completed: x.extraction_initiations_completed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct InfectionStats {
+    alphas_killed: i32,
+    spartans_infected: i32,
+    spartans_infected_as_alpha: i32,
+    kills_as_last_spartan_standing: i32,
+    last_spartan_standing_infected: i32,
+    rounds_as_alpha: i32,
+    rounds_as_last_spartan_standing: i32,
+    rounds_finished_as_infected: i32,
+    rounds_survived_as_spartan: i32,
+    rounds_survived_as_last_spartan_standing: i32,
+    time_as_last_spartan_standing: Duration,
+    time_as_last_spartan_standing_seconds: f64,
+    infected_killed: i32,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsInfectionStats> for InfectionStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsInfectionStats) -> Self {
+        InfectionStats {
+            alphas_killed: x.alphas_killed,
+            spartans_infected: x.spartans_infected,
+            spartans_infected_as_alpha: x.spartans_infected_as_alpha,
+            kills_as_last_spartan_standing: x.kills_as_last_spartan_standing,
+            last_spartan_standing_infected: x.last_spartan_standing_infected,
+            rounds_as_alpha: x.rounds_as_alpha,
+            rounds_as_last_spartan_standing: x.rounds_as_last_spartan_standing,
+            rounds_finished_as_infected: x.rounds_finished_as_infected,
+            rounds_survived_as_spartan: x.rounds_survived_as_spartan,
+            rounds_survived_as_last_spartan_standing: x.rounds_survived_as_last_spartan_standing,
+            time_as_last_spartan_standing_seconds: x.time_as_last_spartan_standing.seconds(),
+            time_as_last_spartan_standing: x.time_as_last_spartan_standing,
+            infected_killed: x.infected_killed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct StockpileStats {
+    kills_as_power_seed_carrier: i32,
+    power_seed_carriers_killed: i32,
+    power_seeds_deposited: i32,
+    power_seeds_stolen: i32,
+    time_as_power_seed_carrier: Duration,
+    time_as_power_seed_carrier_seconds: f64,
+    time_as_power_seed_driver: Duration,
+    time_as_power_seed_driver_seconds: f64,
+}
+
+impl From<halo_requests::MatchStatsTeamStatsStockpileStats> for StockpileStats {
+    fn from(x: halo_requests::MatchStatsTeamStatsStockpileStats) -> Self {
+        StockpileStats {
+            kills_as_power_seed_carrier: x.kills_as_power_seed_carrier,
+            power_seed_carriers_killed: x.power_seed_carriers_killed,
+            power_seeds_deposited: x.power_seeds_deposited,
+            power_seeds_stolen: x.power_seeds_stolen,
+            time_as_power_seed_carrier_seconds: x.time_as_power_seed_carrier.seconds(),
+            time_as_power_seed_carrier: x.time_as_power_seed_carrier,
+            time_as_power_seed_driver_seconds: x.time_as_power_seed_driver.seconds(),
+            time_as_power_seed_driver: x.time_as_power_seed_driver,
+        }
+    }
 }
 
 struct TeamPlayerConnection;
@@ -230,107 +898,262 @@ impl EdgeNameType for TeamPlayerEdge {
 }
 
 #[derive(SimpleObject)]
+#[graphql(complex)]
 struct ScoreChange {
     name_id: i64,
     count: i32,
     total_personal_score_awarded: i32,
 }
 
-#[ComplexObject]
-impl PlayerEdgeData {
-    async fn pre_match_csr<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Csr> {
-        let data = ctx.data_unchecked::<AuthData>();
+#[derive(SimpleObject)]
+struct Film {
+    asset_id: String,
+    duration_milliseconds: i64,
+    has_game_ended: bool,
+    chunks: Vec<FilmChunk>,
+    /// Upstream fields not modelled by this API
+    extra: Value,
+}
 
-        data.loader
-            .load_one(SkillEntry {
-                player_id: self
-                    .player_id
-                    .trim_start_matches("xuid(")
-                    .trim_end_matches(")")
-                    .to_string(),
-                match_id: self.match_id.clone(),
-            })
-            .await?
-            .ok_or(async_graphql::Error::new("Failed to fetch"))
-            .map(|res| Csr {
-                value: res.result.rank_recap.pre_match_csr.value,
-                measurement_matches_remaining: res
-                    .result
-                    .rank_recap
-                    .pre_match_csr
-                    .measurement_matches_remaining,
-                tier: res.result.rank_recap.pre_match_csr.tier,
-                tier_start: res.result.rank_recap.pre_match_csr.tier_start,
-                sub_tier: res.result.rank_recap.pre_match_csr.sub_tier,
-                next_tier: res.result.rank_recap.pre_match_csr.next_tier,
-                next_tier_start: res.result.rank_recap.pre_match_csr.next_tier_start,
-                next_sub_tier: res.result.rank_recap.pre_match_csr.next_sub_tier,
-                initial_measurement_matches: res
-                    .result
-                    .rank_recap
-                    .pre_match_csr
-                    .initial_measurement_matches,
-            })
+#[derive(SimpleObject)]
+struct FilmChunk {
+    index: i32,
+    chunk_type: i32,
+    start_time_offset_milliseconds: i64,
+    duration_milliseconds: i64,
+    size: i64,
+    /// Path on this server that streams the chunk, authenticated with the
+    /// same `spartan_token` header
+    url: String,
+}
+
+/// Background archiving of a player's full match history
+#[derive(SimpleObject)]
+struct Ingestion {
+    xuid: String,
+    gamertag: String,
+    /// Xuid of whoever enrolled the player
+    enrolled_by: String,
+    enrolled_at: DateTime<Utc>,
+    /// Matches walked back from the newest so far
+    backfill_offset: usize,
+    /// Whether the backfill has reached the first match
+    backfill_complete: bool,
+    matches_ingested: usize,
+    last_sync: Option<DateTime<Utc>>,
+    /// Error that stopped the last sync
+    last_error: Option<String>,
+    /// Whether the refresh token was turned down, in which case syncing is
+    /// stopped until the player is enrolled again
+    credential_expired: bool,
+}
+
+impl From<Enrollment> for Ingestion {
+    fn from(x: Enrollment) -> Self {
+        Ingestion {
+            xuid: x.xuid,
+            gamertag: x.gamertag,
+            enrolled_by: x.enrolled_by,
+            enrolled_at: x.enrolled_at,
+            backfill_offset: x.backfill_offset,
+            backfill_complete: x.backfill_complete,
+            matches_ingested: x.matches_ingested,
+            last_sync: x.last_sync,
+            last_error: x.last_error,
+            credential_expired: x.credential_expired,
+        }
     }
+}
 
-    async fn post_match_csr<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Csr> {
-        let data = ctx.data_unchecked::<AuthData>();
+#[derive(SimpleObject, Clone)]
+pub struct Season {
+    /// Also the `seasonId` of matches played during the season
+    id: String,
+    name: String,
+    description: String,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    csr_season_path: String,
+    operation_track_path: String,
+}
 
-        data.loader
-            .load_one(SkillEntry {
-                player_id: self
-                    .player_id
-                    .trim_start_matches("xuid(")
-                    .trim_end_matches(")")
-                    .to_string(),
-                match_id: self.match_id.clone(),
-            })
-            .await?
-            .ok_or(async_graphql::Error::new("Failed to fetch"))
-            .map(|res| Csr {
-                value: res.result.rank_recap.post_match_csr.value,
-                measurement_matches_remaining: res
-                    .result
-                    .rank_recap
-                    .post_match_csr
-                    .measurement_matches_remaining,
-                tier: res.result.rank_recap.post_match_csr.tier,
-                tier_start: res.result.rank_recap.post_match_csr.tier_start,
-                sub_tier: res.result.rank_recap.post_match_csr.sub_tier,
-                next_tier: res.result.rank_recap.post_match_csr.next_tier,
-                next_tier_start: res.result.rank_recap.post_match_csr.next_tier_start,
-                next_sub_tier: res.result.rank_recap.post_match_csr.next_sub_tier,
-                initial_measurement_matches: res
-                    .result
-                    .rank_recap
-                    .post_match_csr
-                    .initial_measurement_matches,
-            })
+#[derive(SimpleObject)]
+struct Medal {
+    name_id: i64,
+    name: String,
+    description: String,
+    difficulty: Option<String>,
+    #[graphql(name = "type")]
+    medal_type: Option<String>,
+    sprite_index: i32,
+    /// Small medal sprite sheet, indexed by `sprite_index`
+    sprite_url: Option<String>,
+    sorting_weight: i32,
+    personal_score: i32,
+}
+
+impl Medal {
+    fn new(metadata: &halo_requests::MedalMetadata, x: &halo_requests::MedalMetadataMedal) -> Self {
+        Medal {
+            name_id: x.name_id,
+            name: x.name.value.clone(),
+            description: x.description.value.clone(),
+            difficulty: metadata.difficulties.get(x.difficulty_index).cloned(),
+            medal_type: metadata.types.get(x.type_index).cloned(),
+            sprite_index: x.sprite_index,
+            sprite_url: metadata
+                .sprites
+                .get("small")
+                .map(|sprite| format!("{}/{}", halo_requests::GAMECMS_FILE_URL, sprite.path)),
+            sorting_weight: x.sorting_weight,
+            personal_score: x.personal_score,
+        }
     }
+}
 
-    async fn expected_kills<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<f32>> {
-        let data = ctx.data_unchecked::<AuthData>();
+impl ScoreChange {
+    async fn medal(&self, ctx: &Context<'_>) -> Result<Option<Medal>> {
+        let metadata = ctx.data_unchecked::<AuthData>().medal_metadata().await?;
 
-        data.loader
-            .load_one(SkillEntry {
-                player_id: self
-                    .player_id
-                    .trim_start_matches("xuid(")
-                    .trim_end_matches(")")
-                    .to_string(),
-                match_id: self.match_id.clone(),
-            })
+        Ok(metadata
+            .medals
+            .iter()
+            .find(|x| x.name_id == self.name_id)
+            .map(|x| Medal::new(metadata, x)))
+    }
+}
+
+#[ComplexObject]
+impl ScoreChange {
+    async fn name<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        Ok(self.medal(ctx).await?.map(|x| x.name))
+    }
+
+    async fn description<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        Ok(self.medal(ctx).await?.map(|x| x.description))
+    }
+
+    async fn difficulty<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        Ok(self.medal(ctx).await?.and_then(|x| x.difficulty))
+    }
+
+    #[graphql(name = "type")]
+    async fn medal_type<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        Ok(self.medal(ctx).await?.and_then(|x| x.medal_type))
+    }
+
+    async fn sprite_index<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<i32>> {
+        Ok(self.medal(ctx).await?.map(|x| x.sprite_index))
+    }
+
+    async fn sprite_url<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        Ok(self.medal(ctx).await?.and_then(|x| x.sprite_url))
+    }
+}
+
+impl From<halo_requests::MatchStatsTeamStatsCoreStatsScore> for ScoreChange {
+    fn from(x: halo_requests::MatchStatsTeamStatsCoreStatsScore) -> Self {
+        ScoreChange {
+            name_id: x.name_id,
+            count: x.count,
+            total_personal_score_awarded: x.total_personal_score_awarded,
+        }
+    }
+}
+
+#[ComplexObject]
+impl PlayerEdgeData {
+    async fn pre_match_csr<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Csr> {
+        Ok(self.skill(ctx).await?.rank_recap.pre_match_csr.into())
+    }
+
+    async fn post_match_csr<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Csr> {
+        Ok(self.skill(ctx).await?.rank_recap.post_match_csr.into())
+    }
+
+    async fn expected_kills<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<f32>> {
+        Ok(self
+            .skill(ctx)
             .await?
-            .ok_or(async_graphql::Error::new("Failed to fetch"))
-            .map(|res| {
-                res.result
-                    .stat_performances
-                    .and_then(|x| x.kills)
-                    .map(|x| x.expected)
-            })
+            .stat_performances
+            .and_then(|x| x.kills)
+            .map(|x| x.expected))
     }
 
     async fn expected_deaths<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<f32>> {
+        Ok(self
+            .skill(ctx)
+            .await?
+            .stat_performances
+            .and_then(|x| x.deaths)
+            .map(|x| x.expected))
+    }
+
+    async fn kills_std_dev<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<f32>> {
+        Ok(self
+            .skill(ctx)
+            .await?
+            .stat_performances
+            .and_then(|x| x.kills)
+            .map(|x| x.std_dev))
+    }
+
+    async fn deaths_std_dev<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<f32>> {
+        Ok(self
+            .skill(ctx)
+            .await?
+            .stat_performances
+            .and_then(|x| x.deaths)
+            .map(|x| x.std_dev))
+    }
+
+    async fn team_mmr<'ctx>(&self, ctx: &Context<'ctx>) -> Result<f32> {
+        Ok(self.skill(ctx).await?.team_mmr)
+    }
+
+    /// MMR of every team in the match
+    async fn team_mmrs<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<TeamMmr>> {
+        Ok(team_mmrs(self.skill(ctx).await?, |_| true))
+    }
+
+    async fn opposing_team_mmrs<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<TeamMmr>> {
+        let skill = self.skill(ctx).await?;
+        let team_id = skill.team_id;
+
+        Ok(team_mmrs(skill, |x| x != team_id))
+    }
+
+    /// Expected kills and deaths against a lobby of this player's own skill
+    async fn self_counterfactuals<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Counterfactual> {
+        Ok(self
+            .skill(ctx)
+            .await?
+            .counterfactuals
+            .self_counterfactuals
+            .into())
+    }
+
+    /// Expected kills and deaths against a lobby of each rank tier
+    async fn tier_counterfactuals<'ctx>(&self, ctx: &Context<'ctx>) -> Result<TierCounterfactuals> {
+        let tiers = self.skill(ctx).await?.counterfactuals.tier_counterfactuals;
+
+        Ok(TierCounterfactuals {
+            bronze: tiers.bronze.into(),
+            silver: tiers.silver.into(),
+            gold: tiers.gold.into(),
+            platinum: tiers.platinum.into(),
+            diamond: tiers.diamond.into(),
+            onyx: tiers.onyx.into(),
+        })
+    }
+
+    async fn ranked_rewards<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<Value>> {
+        Ok(self.skill(ctx).await?.ranked_rewards)
+    }
+}
+
+impl PlayerEdgeData {
+    async fn skill(&self, ctx: &Context<'_>) -> Result<halo_requests::SkillResult> {
         let data = ctx.data_unchecked::<AuthData>();
 
         data.loader
@@ -338,23 +1161,94 @@ impl PlayerEdgeData {
                 player_id: self
                     .player_id
                     .trim_start_matches("xuid(")
-                    .trim_end_matches(")")
+                    .trim_end_matches(')')
                     .to_string(),
                 match_id: self.match_id.clone(),
             })
             .await?
             .ok_or(async_graphql::Error::new("Failed to fetch"))
-            .map(|res| {
-                res.result
-                    .stat_performances
-                    .and_then(|x| x.deaths)
-                    .map(|x| x.expected)
+            .and_then(|res| match res.result_code {
+                0 => Ok(res.result),
+                // The result is all zeros then, which would pass for real data
+                code => Err(async_graphql::Error::new(format!(
+                    "No skill result for this player, result code {code}"
+                ))),
             })
     }
 }
 
+fn team_mmrs(skill: halo_requests::SkillResult, include: impl Fn(i32) -> bool) -> Vec<TeamMmr> {
+    let mut mmrs: Vec<_> = skill
+        .team_mmrs
+        .into_iter()
+        .filter_map(|(team_id, mmr)| team_id.parse().ok().map(|team_id| TeamMmr { team_id, mmr }))
+        .filter(|x| include(x.team_id))
+        .collect();
+
+    mmrs.sort_by_key(|x| x.team_id);
+
+    mmrs
+}
+
+const DEFAULT_MATCHES_PAGE_SIZE: usize = 24;
+
+/// Largest `first`/`last` accepted by `Player.matches`, and most ids accepted
+/// by `Query.matches`, set with `MATCHES_MAX_PAGE_SIZE`.
+fn max_matches_page_size() -> usize {
+    env::var("MATCHES_MAX_PAGE_SIZE")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(200)
+}
+
 #[ComplexObject]
 impl Player {
+    /// Global id, see `xuid` for the upstream one
+    async fn id(&self) -> ID {
+        NodeId::Player {
+            xuid: self.xuid.clone(),
+        }
+        .into()
+    }
+
+    /// Custom games only, ignoring any `type` in `filter`
+    async fn custom_matches<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        filter: Option<MatchFilter>,
+    ) -> Result<Connection<MatchCursor, Match, EmptyFields, MatchEdgeData>> {
+        let filter = MatchFilter {
+            match_type: Some(MatchType::Custom),
+            ..filter.unwrap_or_default()
+        };
+
+        self.matches(ctx, after, before, first, last, Some(filter))
+            .await
+    }
+
+    /// Matchmade games only, ignoring any `type` in `filter`
+    async fn matchmade_matches<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        filter: Option<MatchFilter>,
+    ) -> Result<Connection<MatchCursor, Match, EmptyFields, MatchEdgeData>> {
+        let filter = MatchFilter {
+            match_type: Some(MatchType::Matchmaking),
+            ..filter.unwrap_or_default()
+        };
+
+        self.matches(ctx, after, before, first, last, Some(filter))
+            .await
+    }
+
     async fn matches<'ctx>(
         &self,
         ctx: &Context<'ctx>,
@@ -362,95 +1256,401 @@ impl Player {
         before: Option<String>,
         first: Option<i32>,
         last: Option<i32>,
-    ) -> Result<Connection<usize, Match, EmptyFields, MatchEdgeData>> {
+        filter: Option<MatchFilter>,
+    ) -> Result<Connection<MatchCursor, Match, EmptyFields, MatchEdgeData>> {
+        let mut filter = filter.unwrap_or_default();
+
+        if let Some(id) = &filter.season {
+            let seasons = ctx.data_unchecked::<AuthData>().seasons().await?;
+            let season = seasons
+                .iter()
+                .find(|x| &x.id == id)
+                .ok_or(async_graphql::Error::new("Unknown season"))?;
+
+            filter.restrict_to(season.start_time, season.end_time);
+        }
+
         query(
             after,
             before,
             first,
             last,
-            |after, before, first, last| async move {
+            |after: Option<MatchCursor>, before: Option<MatchCursor>, first, last| async move {
                 let data = ctx.data_unchecked::<AuthData>();
+                let cursorless = after.is_none() && before.is_none();
+
+                let after = match after {
+                    Some(after) => Some(
+                        after
+                            .resolve(data, &self.xuid, filter.match_type)
+                            .await?
+                            .after(),
+                    ),
+                    None => None,
+                };
+                let before = match before {
+                    Some(before) => Some(
+                        before
+                            .resolve(data, &self.xuid, filter.match_type)
+                            .await?
+                            .before(),
+                    ),
+                    None => None,
+                };
+
+                let max_page_size = max_matches_page_size();
+                let first = first.map(|x| x.min(max_page_size));
+                let last = last.map(|x| x.min(max_page_size));
+                let start = after.unwrap_or(0);
+                let end = match before {
+                    Some(before) => before,
+                    None if first.is_none() && last.is_some() => {
+                        halo_requests::matches_len(
+                            &data.client,
+                            &data.spartan_token,
+                            &self.xuid,
+                            filter.match_type,
+                        )
+                        .await?
+                    }
+                    None => usize::MAX,
+                }
+                .max(start);
+
+                let (results, has_previous, has_next) = match (first, last) {
+                    // The newest page alone may come from the shared cache,
+                    // as nothing live is joined to it
+                    (first, None)
+                        if cursorless
+                            && filter.is_empty()
+                            && first.unwrap_or(DEFAULT_MATCHES_PAGE_SIZE)
+                                < halo_requests::MATCHES_PAGE_SIZE =>
+                    {
+                        let count = first.unwrap_or(DEFAULT_MATCHES_PAGE_SIZE);
+                        let page = halo_requests::first_page(
+                            &data.client,
+                            &data.spartan_token,
+                            &data.first_pages,
+                            &self.xuid,
+                            filter.match_type,
+                        )
+                        .await?;
+                        let has_next = page.results.len() > count;
+
+                        (
+                            page.results.into_iter().take(count).enumerate().collect(),
+                            false,
+                            has_next,
+                        )
+                    }
+                    (None, Some(last)) => {
+                        let res =
+                            history::scan_backward(data, &self.xuid, &filter, start, end, last)
+                                .await?;
+
+                        (res.matches, res.has_more, before.is_some())
+                    }
+                    (first, last) => {
+                        let res = history::scan_forward(
+                            data,
+                            &self.xuid,
+                            &filter,
+                            start,
+                            end,
+                            first.unwrap_or(DEFAULT_MATCHES_PAGE_SIZE),
+                        )
+                        .await?;
+
+                        let mut results = res.matches;
+                        let dropped = last.map_or(0, |last| results.len().saturating_sub(last));
+                        results.drain(..dropped);
+
+                        (results, start > 0 || dropped > 0, res.has_more)
+                    }
+                };
+
+                let mut connection = Connection::new(has_previous, has_next);
+
+                connection
+                    .edges
+                    .extend(results.into_iter().map(|(offset, x)| {
+                        Edge::with_additional_fields(
+                            MatchCursor {
+                                offset,
+                                match_id: x.match_id.clone(),
+                                start_time: x.match_info.start_time,
+                            },
+                            Match::new(x.match_id, x.match_info),
+                            MatchEdgeData {
+                                last_team_id: x.last_team_id,
+                                outcome: x.outcome.into(),
+                                outcome_value: x.outcome,
+                                present_at_end_of_match: x.present_at_end_of_match,
+                                rank: x.rank,
+                            },
+                        )
+                    }));
 
-                let first = first.map(|x| x.min(24));
-                let last = last.map(|x| x.min(24));
-                let mut start = after.map(|after| after + 1).unwrap_or(0);
-                let mut end = before.unwrap_or(10000);
+                Ok::<_, async_graphql::Error>(connection)
+            },
+        )
+        .await
+    }
 
-                if let Some(first) = first {
-                    end = (start + first).min(end);
-                }
+    async fn service_record<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        #[graphql(desc = "Season id.")] season: Option<String>,
+        #[graphql(desc = "Playlist asset id.")] playlist: Option<String>,
+        #[graphql(desc = "Match lifecycle mode, defaults to MATCHMADE.")] mode: Option<
+            LifecycleMode,
+        >,
+    ) -> Result<ServiceRecord> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let mode = mode
+            .unwrap_or(LifecycleMode::Matchmade)
+            .path_name()
+            .ok_or(async_graphql::Error::new("Unsupported mode"))?;
+
+        halo_requests::service_record(
+            &data.client,
+            &data.spartan_token,
+            &self.xuid,
+            mode,
+            season.as_deref(),
+            playlist.as_deref(),
+        )
+        .await
+        .map(|x| ServiceRecord {
+            time_played_seconds: x.time_played.seconds(),
+            time_played: x.time_played,
+            matches_completed: x.matches_completed,
+            wins: x.wins,
+            losses: x.losses,
+            ties: x.ties,
+            core_stats: x.core_stats.into(),
+            stronghold_stats: x.zones_stats.map(StrongholdStats::from),
+        })
+    }
+
+    async fn appearance<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Appearance> {
+        let data = ctx.data_unchecked::<AuthData>();
 
-                if let Some(last) = last {
-                    start = if last > end - start {
-                        start
-                    } else {
-                        end - last
-                    };
+        let (customization, mapping) = futures::try_join!(
+            halo_requests::customization(&data.client, &data.spartan_token, &self.xuid),
+            data.emblem_mapping(),
+        )?;
+        let appearance = customization.appearance;
+        let emblem = appearance.emblem;
+
+        let colors = mapping
+            .get(&emblem.emblem_path)
+            .and_then(|x| x.get(&emblem.configuration_id.to_string()));
+        let image_url = |path: &str| {
+            (!path.is_empty()).then(|| format!("{}/{}", halo_requests::GAMECMS_IMAGE_URL, path))
+        };
+
+        Ok(Appearance {
+            service_tag: appearance.service_tag,
+            emblem: Emblem {
+                image_url: colors.and_then(|x| image_url(&x.emblem_cms_path)),
+                nameplate_image_url: colors.and_then(|x| image_url(&x.nameplate_cms_path)),
+                text_color: colors.map(|x| x.text_color.clone()),
+                item: InventoryItem {
+                    path: emblem.emblem_path,
+                },
+                configuration_id: emblem.configuration_id,
+            },
+            backdrop: InventoryItem::new(appearance.backdrop_image_path),
+            player_title: InventoryItem::new(appearance.player_title_path),
+            armor_core: equipped_core(customization.cores.cores).map(|core| {
+                let theme = core
+                    .themes
+                    .iter()
+                    .find(|x| x.is_equipped)
+                    .or_else(|| core.themes.iter().find(|x| x.is_default))
+                    .or(core.themes.first())
+                    .and_then(|x| InventoryItem::new(x.theme_path.clone()));
+
+                ArmorCore {
+                    core_id: core.core_id,
+                    core_type: core.core_type,
+                    theme,
                 }
+            }),
+        })
+    }
 
-                let fetch_count = (end - start + 1).min(25);
+    /// Archiving progress, if the player is enrolled
+    async fn ingestion<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<Ingestion>> {
+        let store = ctx.data_unchecked::<AuthData>().store()?;
 
-                let res = halo_requests::matches(
-                    &data.client,
-                    &data.spartan_token,
-                    &self.id,
-                    Some(start),
-                    Some(fetch_count),
-                )
-                .await?;
+        Ok(store.enrollment(&self.xuid).await?.map(Ingestion::from))
+    }
 
-                let mut connection = Connection::new(start > 0, res.results.len() == fetch_count);
+    async fn progression(&self) -> Progression {
+        Progression {
+            player_id: self.xuid.clone(),
+        }
+    }
 
-                connection.edges.extend(
-                    res.results
-                        .into_iter()
-                        .take(fetch_count - 1)
-                        .enumerate()
-                        .map(|(ind, x)| {
-                            Edge::with_additional_fields(
-                                start + ind,
-                                Match {
-                                    id: x.match_id,
-                                    clearance_id: x.match_info.clearance_id,
-                                    duration: x.match_info.duration,
-                                    end_time: x.match_info.end_time,
-                                    game_variant_category: x.match_info.game_variant_category,
-                                    gameplay_interaction: x.match_info.gameplay_interaction,
-                                    level_id: x.match_info.level_id,
-                                    lifecycle_mode: x.match_info.lifecycle_mode,
-                                    map_variant: AssetReference {
-                                        asset_id: x.match_info.map_variant.asset_id,
-                                        asset_kind: x.match_info.map_variant.asset_kind,
-                                        version_id: x.match_info.map_variant.version_id,
-                                    },
-                                    playable_duration: x.match_info.playable_duration,
-                                    playlist: x.match_info.playlist,
-                                    playlist_experience: x.match_info.playlist_experience,
-                                    playlist_map_mode_pair: x.match_info.playlist_map_mode_pair,
-                                    season_id: x.match_info.season_id,
-                                    start_time: x.match_info.start_time,
-                                    team_scoring_enabled: x.match_info.team_scoring_enabled,
-                                    teams_enabled: x.match_info.teams_enabled,
-                                    ugc_game_variant: AssetReference {
-                                        asset_id: x.match_info.ugc_game_variant.asset_id,
-                                        asset_kind: x.match_info.ugc_game_variant.asset_kind,
-                                        version_id: x.match_info.ugc_game_variant.version_id,
-                                    },
-                                },
-                                MatchEdgeData {
-                                    last_team_id: x.last_team_id,
-                                    outcome: x.outcome,
-                                    present_at_end_of_match: x.present_at_end_of_match,
-                                    rank: x.rank,
-                                },
-                            )
-                        }),
-                );
+    async fn playlist_csr<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        #[graphql(desc = "Playlist asset id.")] playlist_id: String,
+    ) -> Result<PlaylistCsr> {
+        let data = ctx.data_unchecked::<AuthData>();
 
-                Ok::<_, async_graphql::Error>(connection)
-            },
-        )
-        .await
+        data.loader
+            .load_one(PlaylistCsrEntry {
+                player_id: self.xuid.clone(),
+                playlist_id,
+            })
+            .await?
+            .ok_or(async_graphql::Error::new("Failed to fetch"))
+            .and_then(|res| match res.result_code {
+                0 => Ok(PlaylistCsr {
+                    current: res.result.current.into(),
+                    season_max: res.result.season_max.into(),
+                    all_time_max: res.result.all_time_max.into(),
+                }),
+                // The result is all zeros then, which would pass for real data
+                code => Err(async_graphql::Error::new(format!(
+                    "No CSR for this playlist, result code {code}"
+                ))),
+            })
+    }
+}
+
+impl AssetReference {
+    async fn metadata(&self, ctx: &Context<'_>) -> Result<halo_requests::Asset> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        data.loader
+            .load_one(AssetEntry {
+                kind: self
+                    .asset_kind
+                    .path_name()
+                    .ok_or(async_graphql::Error::new("Unsupported asset kind"))?,
+                asset_id: self.asset_id.clone(),
+                version_id: self.version_id.clone(),
+            })
+            .await?
+            .ok_or(async_graphql::Error::new("Failed to fetch"))
+    }
+}
+
+#[ComplexObject]
+impl AssetReference {
+    async fn name<'ctx>(&self, ctx: &Context<'ctx>) -> Result<String> {
+        Ok(self.metadata(ctx).await?.public_name)
+    }
+
+    async fn description<'ctx>(&self, ctx: &Context<'ctx>) -> Result<String> {
+        Ok(self.metadata(ctx).await?.description)
+    }
+
+    async fn version_number<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<i32>> {
+        Ok(self.metadata(ctx).await?.version_number)
+    }
+
+    async fn tags<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<String>> {
+        Ok(self.metadata(ctx).await?.tags)
+    }
+
+    async fn image_urls<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<String>> {
+        let files = self.metadata(ctx).await?.files;
+
+        Ok(files
+            .file_relative_paths
+            .iter()
+            .filter(|x| x.starts_with("images/"))
+            .map(|x| format!("{}{}", files.prefix, x))
+            .collect())
+    }
+
+    async fn thumbnail_url<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        let files = self.metadata(ctx).await?.files;
+
+        Ok(files
+            .file_relative_paths
+            .iter()
+            .find(|x| x.starts_with("images/thumbnail"))
+            .map(|x| format!("{}{}", files.prefix, x)))
+    }
+}
+
+#[ComplexObject]
+impl Team {
+    /// Global id
+    async fn id(&self) -> ID {
+        NodeId::Team {
+            match_id: self.match_id.clone(),
+            team_id: self.team_id,
+        }
+        .into()
+    }
+}
+
+#[ComplexObject]
+impl CustomGame {
+    /// Everyone who took part, including players who left early
+    async fn roster<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<LobbyMember>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let res = data.stats(&self.match_id).await?;
+
+        Ok(res
+            .players
+            .into_iter()
+            .map(|x| LobbyMember {
+                is_bot: x.is_bot(),
+                player_id: x.player_id,
+                team_id: x.last_team_id,
+                present_at_beginning: x.participation_info.present_at_beginning,
+                present_at_completion: x.participation_info.present_at_completion,
+                joined_in_progress: x.participation_info.joined_in_progress,
+                left_in_progress: x.participation_info.left_in_progress,
+            })
+            .collect())
+    }
+}
+
+impl InventoryItem {
+    async fn metadata(&self, ctx: &Context<'_>) -> Result<halo_requests::InventoryItem> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        data.loader
+            .load_one(InventoryItemEntry {
+                path: self.path.clone(),
+            })
+            .await?
+            .ok_or(async_graphql::Error::new("Failed to fetch"))
+    }
+}
+
+#[ComplexObject]
+impl InventoryItem {
+    async fn title<'ctx>(&self, ctx: &Context<'ctx>) -> Result<String> {
+        Ok(self.metadata(ctx).await?.common_data.title.value)
+    }
+
+    async fn description<'ctx>(&self, ctx: &Context<'ctx>) -> Result<String> {
+        Ok(self.metadata(ctx).await?.common_data.description.value)
+    }
+
+    async fn image_url<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<String>> {
+        let path = self
+            .metadata(ctx)
+            .await?
+            .common_data
+            .display_path
+            .media
+            .media_url
+            .path;
+
+        Ok((!path.is_empty()).then(|| format!("{}/{}", halo_requests::GAMECMS_IMAGE_URL, path)))
     }
 }
 
@@ -474,21 +1674,47 @@ impl Player {
 //     }
 // }
 
-#[ComplexObject]
 impl Match {
-    async fn teams<'ctx>(
-        &self,
-        ctx: &Context<'ctx>,
-    ) -> Result<Connection<usize, Team, EmptyFields, TeamEdgeData>> {
-        let data = ctx.data_unchecked::<AuthData>();
-
-        let res = halo_requests::stats(&data.client, &data.spartan_token, &self.id).await?;
+    fn new(match_id: String, info: halo_requests::MatchInfo) -> Self {
+        Match {
+            match_id,
+            clearance_id: info.clearance_id,
+            duration_seconds: info.duration.seconds(),
+            duration: info.duration,
+            end_time: info.end_time,
+            game_variant_category: info.game_variant_category.into(),
+            game_variant_category_value: info.game_variant_category,
+            gameplay_interaction: info.gameplay_interaction.into(),
+            gameplay_interaction_value: info.gameplay_interaction,
+            level_id: info.level_id,
+            lifecycle_mode: info.lifecycle_mode.into(),
+            lifecycle_mode_value: info.lifecycle_mode,
+            map_variant: info.map_variant.into(),
+            playable_duration_seconds: info.playable_duration.seconds(),
+            playable_duration: info.playable_duration,
+            playlist: info.playlist.map(AssetReference::from),
+            playlist_experience: info.playlist_experience,
+            playlist_map_mode_pair: info.playlist_map_mode_pair.map(AssetReference::from),
+            season_id: info.season_id,
+            start_time: info.start_time,
+            team_scoring_enabled: info.team_scoring_enabled,
+            teams_enabled: info.teams_enabled,
+            ugc_game_variant: info.ugc_game_variant.into(),
+            extra: Value::Object(info.extra),
+        }
+    }
 
+    /// Teams of the match along with their players, built from its stats
+    fn team_connection(
+        &self,
+        res: halo_requests::MatchStats,
+    ) -> Connection<usize, Team, EmptyFields, TeamEdgeData> {
         let mut connection = Connection::new(false, false);
 
         connection
             .edges
             .extend(res.teams.into_iter().enumerate().map(|(ind, x)| {
+                let mode_stats = ModeStats::new(self.game_variant_category, &x.stats);
                 let mut player_connection = Connection::new(false, false);
 
                 player_connection
@@ -496,219 +1722,395 @@ impl Match {
                     .extend(res.players.iter().flat_map(|y| {
                         let stats = y.player_team_stats.iter().find(|y| y.team_id == x.team_id);
 
-                        match stats {
-                            Some(x) => Some(Edge::with_additional_fields(
+                        stats.map(|x| {
+                            Edge::with_additional_fields(
                                 0,
                                 None,
                                 TeamPlayerEdgeData {
                                     player_id: y.player_id.clone(),
-                                    score: x.stats.core_stats.score,
-                                    personal_score: x.stats.core_stats.personal_score,
-                                    rounds_won: x.stats.core_stats.rounds_won,
-                                    rounds_lost: x.stats.core_stats.rounds_lost,
-                                    rounds_tied: x.stats.core_stats.rounds_tied,
-                                    kills: x.stats.core_stats.kills,
-                                    deaths: x.stats.core_stats.deaths,
-                                    assists: x.stats.core_stats.assists,
-                                    kda: x.stats.core_stats.kda,
-                                    suicides: x.stats.core_stats.suicides,
-                                    betrayals: x.stats.core_stats.betrayals,
-                                    average_life_duration: x
-                                        .stats
-                                        .core_stats
-                                        .average_life_duration
-                                        .clone(),
-                                    grenade_kills: x.stats.core_stats.grenade_kills,
-                                    headshot_kills: x.stats.core_stats.headshot_kills,
-                                    melee_kills: x.stats.core_stats.melee_kills,
-                                    power_weapon_kills: x.stats.core_stats.power_weapon_kills,
-                                    shots_fired: x.stats.core_stats.shots_fired,
-                                    shots_hit: x.stats.core_stats.shots_hit,
-                                    accuracy: x.stats.core_stats.accuracy,
-                                    damage_dealt: x.stats.core_stats.damage_dealt,
-                                    damage_taken: x.stats.core_stats.damage_taken,
-                                    callout_assists: x.stats.core_stats.callout_assists,
-                                    vehicle_destroys: x.stats.core_stats.vehicle_destroys,
-                                    driver_assists: x.stats.core_stats.driver_assists,
-                                    hijacks: x.stats.core_stats.hijacks,
-                                    emp_assists: x.stats.core_stats.emp_assists,
-                                    max_killing_spree: x.stats.core_stats.max_killing_spree,
-                                    medals: x
-                                        .stats
-                                        .core_stats
-                                        .medals
-                                        .iter()
-                                        .map(|x| ScoreChange {
-                                            count: x.count,
-                                            total_personal_score_awarded: x
-                                                .total_personal_score_awarded,
-                                            name_id: x.name_id,
-                                        })
-                                        .collect(),
-                                    personal_scores: x
+                                    is_bot: y.is_bot(),
+                                    core_stats: x.stats.core_stats.clone().into(),
+                                    stronghold_stats: x
                                         .stats
-                                        .core_stats
-                                        .personal_scores
-                                        .iter()
-                                        .map(|x| ScoreChange {
-                                            count: x.count,
-                                            total_personal_score_awarded: x
-                                                .total_personal_score_awarded,
-                                            name_id: x.name_id,
-                                        })
-                                        .collect(),
-                                    deprecated_damage_dealt: x
-                                        .stats
-                                        .core_stats
-                                        .deprecated_damage_dealt,
-                                    deprecated_damage_taken: x
-                                        .stats
-                                        .core_stats
-                                        .deprecated_damage_taken,
-                                    spawns: x.stats.core_stats.spawns,
-                                    objectives_completed: x.stats.core_stats.objectives_completed,
-                                    stronghold_stats: x.stats.zones_stats.as_ref().map(|x| {
-                                        StrongholdStats {
-                                            captures: x.stronghold_captures,
-                                            defensive_kills: x.stronghold_defensive_kills,
-                                            offensive_kills: x.stronghold_offensive_kills,
-                                            secures: x.stronghold_secures,
-                                            occupation_time: x.stronghold_occupation_time.clone(),
-                                            scoring_ticks: x.stronghold_scoring_ticks,
-                                        }
-                                    }),
+                                        .zones_stats
+                                        .clone()
+                                        .map(StrongholdStats::from),
+                                    mode_stats: ModeStats::new(
+                                        self.game_variant_category,
+                                        &x.stats,
+                                    ),
+                                    extra_mode_stats: Value::Object(x.stats.extra.clone()),
                                 },
-                            )),
-                            _ => None,
-                        }
+                            )
+                        })
                     }));
 
                 Edge::with_additional_fields(
                     ind,
                     Team {
+                        match_id: self.match_id.clone(),
                         team_id: x.team_id,
                         rank: x.rank,
                         players: player_connection,
                     },
                     TeamEdgeData {
-                        outcome: x.outcome,
-                        score: x.stats.core_stats.score,
+                        outcome: x.outcome.into(),
+                        outcome_value: x.outcome,
                         total_personal_score: x.stats.core_stats.personal_score,
-                        rounds_won: x.stats.core_stats.rounds_won,
-                        rounds_lost: x.stats.core_stats.rounds_lost,
-                        rounds_tied: x.stats.core_stats.rounds_tied,
-                        kills: x.stats.core_stats.kills,
-                        deaths: x.stats.core_stats.deaths,
-                        assists: x.stats.core_stats.assists,
-                        kda: x.stats.core_stats.kda,
-                        suicides: x.stats.core_stats.suicides,
-                        betrayals: x.stats.core_stats.betrayals,
-                        average_life_duration: x.stats.core_stats.average_life_duration,
-                        grenade_kills: x.stats.core_stats.grenade_kills,
-                        headshot_kills: x.stats.core_stats.headshot_kills,
-                        melee_kills: x.stats.core_stats.melee_kills,
-                        power_weapon_kills: x.stats.core_stats.power_weapon_kills,
-                        shots_fired: x.stats.core_stats.shots_fired,
-                        shots_hit: x.stats.core_stats.shots_hit,
-                        accuracy: x.stats.core_stats.accuracy,
-                        damage_dealt: x.stats.core_stats.damage_dealt,
-                        damage_taken: x.stats.core_stats.damage_taken,
-                        callout_assists: x.stats.core_stats.callout_assists,
-                        vehicle_destroys: x.stats.core_stats.vehicle_destroys,
-                        driver_assists: x.stats.core_stats.driver_assists,
-                        hijacks: x.stats.core_stats.hijacks,
-                        emp_assists: x.stats.core_stats.emp_assists,
-                        max_killing_spree: x.stats.core_stats.max_killing_spree,
-                        medals: x
-                            .stats
-                            .core_stats
-                            .medals
-                            .into_iter()
-                            .map(|x| ScoreChange {
-                                count: x.count,
-                                total_personal_score_awarded: x.total_personal_score_awarded,
-                                name_id: x.name_id,
-                            })
-                            .collect(),
-                        personal_scores: x
-                            .stats
-                            .core_stats
-                            .personal_scores
-                            .into_iter()
-                            .map(|x| ScoreChange {
-                                count: x.count,
-                                total_personal_score_awarded: x.total_personal_score_awarded,
-                                name_id: x.name_id,
-                            })
-                            .collect(),
-                        deprecated_damage_dealt: x.stats.core_stats.deprecated_damage_dealt,
-                        deprecated_damage_taken: x.stats.core_stats.deprecated_damage_taken,
-                        spawns: x.stats.core_stats.spawns,
-                        objectives_completed: x.stats.core_stats.objectives_completed,
-                        stronghold_stats: x.stats.zones_stats.map(|x| StrongholdStats {
-                            captures: x.stronghold_captures,
-                            defensive_kills: x.stronghold_defensive_kills,
-                            offensive_kills: x.stronghold_offensive_kills,
-                            secures: x.stronghold_secures,
-                            occupation_time: x.stronghold_occupation_time,
-                            scoring_ticks: x.stronghold_scoring_ticks,
-                        }),
+                        core_stats: x.stats.core_stats.into(),
+                        stronghold_stats: x.stats.zones_stats.map(StrongholdStats::from),
+                        mode_stats,
+                        extra_mode_stats: Value::Object(x.stats.extra),
                     },
                 )
             }));
 
-        Ok(connection)
+        connection
+    }
+}
+
+#[ComplexObject]
+impl Match {
+    /// Global id, see `matchId` for the upstream one
+    async fn id(&self) -> ID {
+        NodeId::Match {
+            match_id: self.match_id.clone(),
+        }
+        .into()
+    }
+
+    async fn map(&self) -> &AssetReference {
+        &self.map_variant
+    }
+
+    async fn game_variant(&self) -> &AssetReference {
+        &self.ugc_game_variant
+    }
+
+    /// Lobby info, only set for custom games
+    async fn custom_game(&self) -> Option<CustomGame> {
+        (self.lifecycle_mode == LifecycleMode::Custom).then(|| CustomGame {
+            match_id: self.match_id.clone(),
+            map: self.map_variant.clone(),
+            game_variant: self.ugc_game_variant.clone(),
+            teams_enabled: self.teams_enabled,
+            team_scoring_enabled: self.team_scoring_enabled,
+        })
+    }
+
+    /// Theater film, if one was recorded
+    async fn film<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<Film>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let film = halo_requests::film(&data.client, &data.spartan_token, &self.match_id).await?;
+
+        Ok(film.map(|x| Film {
+            asset_id: x.asset_id,
+            duration_milliseconds: x.custom_data.film_length,
+            has_game_ended: x.custom_data.has_game_ended,
+            chunks: x
+                .custom_data
+                .chunks
+                .into_iter()
+                .map(|y| FilmChunk {
+                    url: format!("/films/{}/chunks/{}", self.match_id, y.index),
+                    index: y.index,
+                    chunk_type: y.chunk_type,
+                    start_time_offset_milliseconds: y.chunk_start_time_offset_milliseconds,
+                    duration_milliseconds: y.duration_milliseconds,
+                    size: y.chunk_size,
+                })
+                .collect(),
+            extra: Value::Object(x.extra),
+        }))
+    }
+
+    /// Season the match was played in, by id or else by start time
+    async fn season<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Option<Season>> {
+        let seasons = ctx.data_unchecked::<AuthData>().seasons().await?;
+        let id = self.season_id.as_ref().and_then(|x| x.as_str());
+
+        Ok(seasons
+            .iter()
+            .find(|x| Some(x.id.as_str()) == id)
+            .or_else(|| {
+                seasons
+                    .iter()
+                    .find(|x| x.start_time <= self.start_time && self.start_time < x.end_time)
+            })
+            .cloned())
+    }
+
+    async fn teams<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+    ) -> Result<Connection<usize, Team, EmptyFields, TeamEdgeData>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        Ok(self.team_connection(data.stats(&self.match_id).await?))
+    }
+
+    async fn players<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+    ) -> Result<Connection<usize, Option<Player>, EmptyFields, PlayerEdgeData>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        // data.loader.load_one(10 as f32).await
+
+        let res = data.stats(&self.match_id).await?;
+
+        let mut connection = Connection::new(false, false);
+
+        connection
+            .edges
+            .extend(res.players.into_iter().enumerate().map(|(ind, x)| {
+                let is_bot = x.is_bot();
+
+                Edge::with_additional_fields(
+                    ind,
+                    None,
+                    PlayerEdgeData {
+                        match_id: self.match_id.clone(),
+                        player_id: x.player_id,
+                        player_type: x.player_type.into(),
+                        player_type_value: x.player_type,
+                        is_bot,
+                        bot_attributes: x.bot_attributes.map(BotAttributes::from),
+                        last_team_id: x.last_team_id,
+                        outcome: x.outcome.into(),
+                        outcome_value: x.outcome,
+                        rank: x.rank,
+                        first_joined_time: x.participation_info.first_joined_time,
+                        last_leave_time: x.participation_info.last_leave_time,
+                        present_at_beginning: x.participation_info.present_at_beginning,
+                        joined_in_progress: x.participation_info.joined_in_progress,
+                        left_in_progress: x.participation_info.left_in_progress,
+                        present_at_completion: x.participation_info.present_at_completion,
+                        time_played_seconds: x.participation_info.time_played.seconds(),
+                        time_played: x.participation_info.time_played,
+                        confirmed_participation: x
+                            .participation_info
+                            .confirmed_participation
+                            .map(ConfirmedParticipation::from),
+                        extra: Value::Object(x.extra),
+                    },
+                )
+            }));
+
+        Ok(connection)
+    }
+}
+
+#[Object]
+impl Progression {
+    async fn career_rank<'ctx>(&self, ctx: &Context<'ctx>) -> Result<CareerRank> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let (progress, metadata) = futures::try_join!(
+            halo_requests::career_rank(&data.client, &data.spartan_token, &self.player_id),
+            data.career_rank_metadata(),
+        )?;
+        let progress = progress.current_progress;
+
+        let rank = metadata.ranks.iter().find(|x| x.rank == progress.rank);
+        let next_rank_xp = metadata
+            .ranks
+            .iter()
+            .find(|x| x.rank == progress.rank + 1)
+            .map(|x| x.xp_required_for_rank);
+
+        Ok(CareerRank {
+            rank: progress.rank,
+            title: rank.map(|x| x.rank_title.value.clone()).unwrap_or_default(),
+            subtitle: rank
+                .map(|x| x.rank_sub_title.value.clone())
+                .unwrap_or_default(),
+            tier: rank.map_or(CareerRankTier::Unknown, |x| x.tier_type.as_str().into()),
+            grade: rank.map(|x| x.rank_grade).unwrap_or_default(),
+            xp: progress.partial_progress,
+            next_rank_xp,
+            xp_to_next_rank: next_rank_xp.map(|x| (x - progress.partial_progress).max(0)),
+            has_reached_max_rank: progress.has_reached_max_rank,
+            icon_url: rank
+                .filter(|x| !x.rank_icon.is_empty())
+                .map(|x| format!("{}/{}", halo_requests::GAMECMS_IMAGE_URL, x.rank_icon)),
+        })
+    }
+
+    /// Season and event reward track progress
+    async fn operations<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<RewardTrack>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let res =
+            halo_requests::operations(&data.client, &data.spartan_token, &self.player_id).await?;
+
+        Ok(res
+            .operation_reward_tracks
+            .into_iter()
+            .map(|x| RewardTrack {
+                active: res.active_operation_reward_track_path.as_ref()
+                    == Some(&x.reward_track_path),
+                path: x.reward_track_path,
+                track_type: x.track_type,
+                rank: x.current_progress.rank,
+                xp: x.current_progress.partial_progress,
+                is_owned: x.is_owned,
+                has_reached_max_rank: x.current_progress.has_reached_max_rank,
+            })
+            .collect())
+    }
+}
+
+pub struct HaloLoader {
+    pub client: Client,
+    pub spartan_token: String,
+    pub assets: Arc<LruCache<AssetEntry, halo_requests::Asset>>,
+    pub items: Arc<LruCache<InventoryItemEntry, halo_requests::InventoryItem>>,
+    pub stats: Arc<LruCache<StatsEntry, halo_requests::MatchStats>>,
+    pub skills: Arc<LruCache<SkillEntry, halo_requests::Skill>>,
+    /// Local archive, if `SQLITE_PATH` is set
+    pub store: Option<Arc<Store>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct AssetEntry {
+    kind: &'static str,
+    asset_id: String,
+    version_id: String,
+}
+
+#[async_trait::async_trait]
+impl Loader<AssetEntry> for HaloLoader {
+    type Value = halo_requests::Asset;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[AssetEntry]) -> Result<HashMap<AssetEntry, Self::Value>> {
+        let futures: futures::stream::FuturesUnordered<_> = keys
+            .iter()
+            .map(|key| async move {
+                if let Some(asset) = self.assets.get(key) {
+                    return Some((key.clone(), asset));
+                }
+
+                let asset = halo_requests::asset(
+                    &self.client,
+                    &self.spartan_token,
+                    key.kind,
+                    &key.asset_id,
+                    &key.version_id,
+                )
+                .await
+                .ok()?;
+
+                self.assets.insert(key.clone(), asset.clone());
+
+                Some((key.clone(), asset))
+            })
+            .collect();
+
+        let results: Vec<_> = futures.collect().await;
+
+        Ok(results.into_iter().flatten().collect())
     }
+}
 
-    async fn players<'ctx>(
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct InventoryItemEntry {
+    path: String,
+}
+
+#[async_trait::async_trait]
+impl Loader<InventoryItemEntry> for HaloLoader {
+    type Value = halo_requests::InventoryItem;
+    type Error = async_graphql::Error;
+
+    async fn load(
         &self,
-        ctx: &Context<'ctx>,
-    ) -> Result<Connection<usize, Option<Player>, EmptyFields, PlayerEdgeData>> {
-        let data = ctx.data_unchecked::<AuthData>();
+        keys: &[InventoryItemEntry],
+    ) -> Result<HashMap<InventoryItemEntry, Self::Value>> {
+        let futures: futures::stream::FuturesUnordered<_> = keys
+            .iter()
+            .map(|key| async move {
+                if let Some(item) = self.items.get(key) {
+                    return Some((key.clone(), item));
+                }
 
-        // data.loader.load_one(10 as f32).await
+                let item =
+                    halo_requests::inventory_item(&self.client, &self.spartan_token, &key.path)
+                        .await
+                        .ok()?;
 
-        let res = halo_requests::stats(&data.client, &data.spartan_token, &self.id).await?;
+                self.items.insert(key.clone(), item.clone());
 
-        let mut connection = Connection::new(false, false);
+                Some((key.clone(), item))
+            })
+            .collect();
 
-        connection
-            .edges
-            .extend(res.players.into_iter().enumerate().map(|(ind, x)| {
-                Edge::with_additional_fields(
-                    ind,
-                    None,
-                    PlayerEdgeData {
-                        match_id: self.id.clone(),
-                        player_id: x.player_id,
-                        player_type: x.player_type,
-                        bot_attributes: x.bot_attributes,
-                        last_team_id: x.last_team_id,
-                        outcome: x.outcome,
-                        rank: x.rank,
-                        first_joined_time: x.participation_info.first_joined_time,
-                        last_leave_time: x.participation_info.last_leave_time,
-                        present_at_beginning: x.participation_info.present_at_beginning,
-                        joined_in_progress: x.participation_info.joined_in_progress,
-                        left_in_progress: x.participation_info.left_in_progress,
-                        present_at_completion: x.participation_info.present_at_completion,
-                        time_played: x.participation_info.time_played,
-                        confirmed_participation: x.participation_info.confirmed_participation,
-                    },
-                )
-            }));
+        let results: Vec<_> = futures.collect().await;
 
-        Ok(connection)
+        Ok(results.into_iter().flatten().collect())
     }
 }
 
-pub struct HaloLoader {
-    pub client: Client,
-    pub spartan_token: String,
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct StatsEntry {
+    match_id: String,
+}
+
+#[async_trait::async_trait]
+impl Loader<StatsEntry> for HaloLoader {
+    type Value = halo_requests::MatchStats;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[StatsEntry]) -> Result<HashMap<StatsEntry, Self::Value>> {
+        let futures: futures::stream::FuturesUnordered<_> = keys
+            .iter()
+            .map(|key| async move {
+                if let Some(stats) = self.stats.get(key) {
+                    return Ok((key.clone(), stats));
+                }
+
+                let archived = match &self.store {
+                    Some(store) => store.stats(&key.match_id).await.unwrap_or_else(|err| {
+                        println!("Failed to read match {}: {}", key.match_id, err.message);
+                        None
+                    }),
+                    None => None,
+                };
+
+                let stats = match archived {
+                    Some(stats) => stats,
+                    None => {
+                        let (stats, raw) =
+                            halo_requests::stats(&self.client, &self.spartan_token, &key.match_id)
+                                .await?;
+
+                        if let Some(store) = &self.store {
+                            if let Err(err) = store.save_stats(&stats, &raw).await {
+                                println!(
+                                    "Failed to archive match {}: {}",
+                                    key.match_id, err.message
+                                );
+                            }
+                        }
+
+                        stats
+                    }
+                };
+
+                self.stats.insert(key.clone(), stats.clone());
+
+                Ok::<_, async_graphql::Error>((key.clone(), stats))
+            })
+            .collect();
+
+        futures.try_collect().await
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
-struct SkillEntry {
+pub struct SkillEntry {
     player_id: String,
     match_id: String,
 }
@@ -719,9 +2121,44 @@ impl Loader<SkillEntry> for HaloLoader {
     type Error = async_graphql::Error;
 
     async fn load(&self, keys: &[SkillEntry]) -> Result<HashMap<SkillEntry, Self::Value>> {
+        let mut cached = HashMap::new();
         let mut map: HashMap<String, Vec<String>> = HashMap::new();
 
+        let mut missing = Vec::new();
+
         for x in keys.iter() {
+            match self.skills.get(x) {
+                Some(skill) => {
+                    cached.insert(x.clone(), skill);
+                }
+                None => missing.push(x),
+            }
+        }
+
+        // Looked up in one go rather than a query per player
+        let archived = match &self.store {
+            Some(store) if !missing.is_empty() => store
+                .skills(
+                    &missing
+                        .iter()
+                        .map(|x| (x.match_id.clone(), x.player_id.clone()))
+                        .collect::<Vec<_>>(),
+                )
+                .await
+                .unwrap_or_else(|err| {
+                    println!("Failed to read archived skill: {}", err.message);
+                    HashMap::new()
+                }),
+            _ => HashMap::new(),
+        };
+
+        for x in missing {
+            if let Some(skill) = archived.get(&(x.match_id.clone(), x.player_id.clone())) {
+                self.skills.insert(x.clone(), skill.clone());
+                cached.insert(x.clone(), skill.clone());
+                continue;
+            }
+
             map.entry(x.match_id.clone())
                 .or_default()
                 .push(x.player_id.clone());
@@ -735,16 +2172,25 @@ impl Loader<SkillEntry> for HaloLoader {
                     .map_or_else(
                         |_| Vec::new(),
                         |x| {
+                            // Keyed by the id each result carries, so players
+                            // left out or reordered don't get someone else's
                             x.into_iter()
-                                .enumerate()
-                                .map(|(ind, skill)| {
-                                    (
-                                        SkillEntry {
-                                            player_id: players[ind].clone(),
-                                            match_id: match_id.clone(),
-                                        },
-                                        skill,
-                                    )
+                                .filter_map(|(skill, raw)| {
+                                    let player_id = skill
+                                        .id
+                                        .trim_start_matches("xuid(")
+                                        .trim_end_matches(')')
+                                        .to_string();
+
+                                    players.contains(&player_id).then(|| {
+                                        (
+                                            SkillEntry {
+                                                player_id,
+                                                match_id: match_id.clone(),
+                                            },
+                                            (skill, raw),
+                                        )
+                                    })
                                 })
                                 .collect()
                         },
@@ -754,6 +2200,89 @@ impl Loader<SkillEntry> for HaloLoader {
 
         let results: Vec<_> = futures.collect().await;
 
+        for (key, (skill, raw)) in results.into_iter().flatten() {
+            // Failed results are left for the next request to retry
+            if skill.result_code == 0 {
+                if let Some(store) = &self.store {
+                    if let Err(err) = store
+                        .save_skill(&key.match_id, &key.player_id, &skill, &raw)
+                        .await
+                    {
+                        println!(
+                            "Failed to archive skill for {}: {}",
+                            key.match_id, err.message
+                        );
+                    }
+                }
+
+                self.skills.insert(key.clone(), skill.clone());
+            }
+
+            cached.insert(key, skill);
+        }
+
+        Ok(cached)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PlaylistCsrEntry {
+    player_id: String,
+    playlist_id: String,
+}
+
+#[async_trait::async_trait]
+impl Loader<PlaylistCsrEntry> for HaloLoader {
+    type Value = halo_requests::PlaylistCsr;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[PlaylistCsrEntry],
+    ) -> Result<HashMap<PlaylistCsrEntry, Self::Value>> {
+        let mut map: HashMap<String, Vec<String>> = HashMap::new();
+
+        for x in keys.iter() {
+            map.entry(x.playlist_id.clone())
+                .or_default()
+                .push(x.player_id.clone());
+        }
+
+        let futures: futures::stream::FuturesUnordered<_> = map
+            .into_iter()
+            .map(|(playlist_id, players)| async move {
+                halo_requests::playlist_csr(
+                    &self.client,
+                    &self.spartan_token,
+                    &playlist_id,
+                    &players,
+                )
+                .await
+                .map_or_else(
+                    |_| Vec::new(),
+                    |x| {
+                        x.into_iter()
+                            .map(|csr| {
+                                (
+                                    PlaylistCsrEntry {
+                                        player_id: csr
+                                            .id
+                                            .trim_start_matches("xuid(")
+                                            .trim_end_matches(')')
+                                            .to_string(),
+                                        playlist_id: playlist_id.clone(),
+                                    },
+                                    csr,
+                                )
+                            })
+                            .collect()
+                    },
+                )
+            })
+            .collect();
+
+        let results: Vec<Vec<_>> = futures.collect().await;
+
         Ok(results.into_iter().flat_map(|x| x.into_iter()).collect())
     }
 }
@@ -770,16 +2299,12 @@ impl Query {
         let data = ctx.data_unchecked::<AuthData>();
 
         let auth_token = auth::auth_token(&data.client, code, refresh_token).await?;
-        let user_token = auth::user_token(&data.client, auth_token.access_token).await?;
-        let xsts_token = auth::xsts_token(&data.client, user_token.token)
-            .await?
-            .token;
-        let spartan_token = auth::spartan_token(&data.client, xsts_token).await?;
+        let sign_in = auth::sign_in(&data.client, auth_token).await?;
 
         Ok(SpartanToken {
-            token: spartan_token.spartan_token,
-            expires_at: spartan_token.expires_utc.iso8601_date,
-            refresh_token: auth_token.refresh_token,
+            token: sign_in.spartan_token.spartan_token,
+            expires_at: sign_in.spartan_token.expires_utc.iso8601_date,
+            refresh_token: sign_in.refresh_token,
         })
     }
 
@@ -788,6 +2313,65 @@ impl Query {
         auth::redirect_url()
     }
 
+    /// Medal catalog
+    async fn medals<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<Medal>> {
+        let metadata = ctx.data_unchecked::<AuthData>().medal_metadata().await?;
+
+        Ok(metadata
+            .medals
+            .iter()
+            .map(|x| Medal::new(metadata, x))
+            .collect())
+    }
+
+    /// Looks up a match by id, such as one from a share link
+    #[graphql(name = "match")]
+    async fn match_<'ctx>(&self, ctx: &Context<'ctx>, id: String) -> Result<Match> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let res = data.stats(&id).await?;
+
+        Ok(Match::new(res.match_id, res.match_info))
+    }
+
+    /// Looks up several matches by id, with `null` for any that failed
+    async fn matches<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        ids: Vec<String>,
+    ) -> Result<Vec<Option<Match>>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        if ids.len() > max_matches_page_size() {
+            return Err(async_graphql::Error::new("Too many ids"));
+        }
+
+        Ok(futures::future::join_all(ids.iter().map(|id| async move {
+            data.stats(id)
+                .await
+                .ok()
+                .map(|x| Match::new(x.match_id, x.match_info))
+        }))
+        .await)
+    }
+
+    /// Players enrolled for archiving
+    async fn ingestions<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<Ingestion>> {
+        let store = ctx.data_unchecked::<AuthData>().store()?;
+
+        Ok(store
+            .enrollments()
+            .await?
+            .into_iter()
+            .map(Ingestion::from)
+            .collect())
+    }
+
+    /// Season calendar
+    async fn seasons<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<Season>> {
+        ctx.data_unchecked::<AuthData>().seasons().await
+    }
+
     // async fn matches<'ctx>(
     //     &self,
     //     ctx: &Context<'ctx>,
@@ -850,66 +2434,478 @@ impl Query {
     async fn player<'ctx>(&self, ctx: &Context<'ctx>, gamertag: String) -> Result<Player> {
         let data = ctx.data::<AuthData>().unwrap();
 
-        halo_requests::gamer(&data.client, &data.spartan_token, &gamertag)
-            .await
-            .map(|x| Player {
-                id: x.xuid,
-                gamertag: x.gamertag,
-                pic: PlayerPic {
-                    small: x.gamerpic.small,
-                    medium: x.gamerpic.medium,
-                    large: x.gamerpic.large,
-                    xlarge: x.gamerpic.xlarge,
-                },
+        let client = data.client.clone();
+        let spartan_token = data.spartan_token.clone();
+        let key = format!("gt({})", gamertag.to_lowercase());
+
+        data.gamers
+            .get_or_fetch(key, move || async move {
+                halo_requests::gamer(&client, &spartan_token, &gamertag).await
             })
+            .await
+            .map(Player::from)
+    }
+
+    /// Refetches an object by its global id
+    async fn node<'ctx>(&self, ctx: &Context<'ctx>, id: ID) -> Result<Option<Node>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        match NodeId::try_from(&id)? {
+            NodeId::Player { xuid } => data
+                .gamer_by_xuid(xuid)
+                .await
+                .map(|x| Some(Node::Player(x.into()))),
+            id => match id.match_id() {
+                Some(match_id) => {
+                    let res = data.stats(match_id).await?;
+
+                    Ok(Node::from_stats(id, res))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// Refetches several objects, with `null` for any that failed. Matches
+    /// and teams are loaded in one batch.
+    async fn nodes<'ctx>(&self, ctx: &Context<'ctx>, ids: Vec<ID>) -> Vec<Option<Node>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let ids: Vec<_> = ids.iter().map(|x| NodeId::try_from(x).ok()).collect();
+        let stats = data
+            .loader
+            .load_many(ids.iter().flatten().filter_map(|x| {
+                x.match_id().map(|match_id| StatsEntry {
+                    match_id: match_id.to_string(),
+                })
+            }))
+            .await
+            .unwrap_or_else(|err| {
+                println!("Failed to fetch nodes: {}", err.message);
+                HashMap::new()
+            });
+
+        futures::future::join_all(ids.into_iter().map(|id| async {
+            match id? {
+                NodeId::Player { xuid } => data
+                    .gamer_by_xuid(xuid)
+                    .await
+                    .ok()
+                    .map(|x| Node::Player(x.into())),
+                id => {
+                    let res = stats.get(&StatsEntry {
+                        match_id: id.match_id()?.to_string(),
+                    })?;
+
+                    Node::from_stats(id, res.clone())
+                }
+            }
+        }))
+        .await
+    }
+}
+
+#[Object]
+impl Mutation {
+    /// Starts archiving a player's full match history in the background,
+    /// signing in with an OAuth refresh token from `spartanToken`. The token
+    /// is kept sealed and renewed as it's used. Enrolling again replaces it,
+    /// which only the player and whoever enrolled them can do.
+    async fn enroll_player<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        gamertag: String,
+        #[graphql(desc = "OAuth refresh token.")] refresh_token: String,
+    ) -> Result<Ingestion> {
+        let data = ctx.data_unchecked::<AuthData>();
+        let store = data.store()?;
+
+        let (caller, gamer) = futures::try_join!(
+            halo_requests::me(&data.client, &data.spartan_token),
+            halo_requests::gamer(&data.client, &data.spartan_token, &gamertag),
+        )?;
+
+        if let Some(enrollment) = store.enrollment(&gamer.xuid).await? {
+            if !enrollment.is_managed_by(&caller.xuid) {
+                return Err(async_graphql::Error::new(
+                    "Only the player or whoever enrolled them can change the enrollment",
+                ));
+            }
+        }
+
+        // Signing in checks the token and gets a fresh one to keep
+        let sign_in = auth::refresh(&data.client, &refresh_token)
+            .await?
+            .ok_or(async_graphql::Error::new("Refresh token was turned down"))?;
+
+        Ok(store
+            .enroll(
+                &gamer.xuid,
+                &gamer.gamertag,
+                &caller.xuid,
+                &sign_in.refresh_token,
+            )
+            .await?
+            .into())
+    }
+
+    /// Stops archiving a player, keeping what was archived so far. Only the
+    /// player and whoever enrolled them can do this.
+    async fn unenroll_player<'ctx>(&self, ctx: &Context<'ctx>, xuid: String) -> Result<bool> {
+        let data = ctx.data_unchecked::<AuthData>();
+        let store = data.store()?;
+
+        let caller = halo_requests::me(&data.client, &data.spartan_token).await?;
+
+        match store.enrollment(&xuid).await? {
+            Some(enrollment) if enrollment.is_managed_by(&caller.xuid) => {
+                store.unenroll(&xuid).await
+            }
+            Some(_) => Err(async_graphql::Error::new(
+                "Only the player or whoever enrolled them can change the enrollment",
+            )),
+            None => Ok(false),
+        }
+    }
+}
+
+#[Subscription]
+impl Subscription {
+    /// Emits each match the player completes from now on
+    async fn match_completed<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        gamertag: String,
+    ) -> Result<impl Stream<Item = Result<Match>>> {
+        let data = ctx.data_unchecked::<AuthData>();
+
+        let gamer = halo_requests::gamer(&data.client, &data.spartan_token, &gamertag).await?;
+        let receiver = data
+            .watcher
+            .subscribe(&gamer.xuid, &data.spartan_token)
+            .await?;
+
+        // Ends after an error, since the watch is over by then
+        Ok(futures::stream::unfold(
+            Some(receiver),
+            |receiver| async move {
+                let mut receiver = receiver?;
+
+                match receiver.recv().await? {
+                    Ok(x) => Some((Ok(Match::new(x.match_id, x.match_info)), Some(receiver))),
+                    Err(err) => Some((Err(err), None)),
+                }
+            },
+        ))
     }
 }
 
 async fn index_graphiql() -> actix_web::Result<HttpResponse> {
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
-        .body(GraphiQLSource::build().endpoint("/").finish()))
+        .body(
+            GraphiQLSource::build()
+                .endpoint("/")
+                .subscription_endpoint("/ws")
+                .finish(),
+        ))
 }
 
-async fn index(
-    data: web::Data<ActixData>,
-    req: HttpRequest,
-    request: GraphQLRequest,
-) -> GraphQLResponse {
-    let spartan_token = req
-        .headers()
+fn spartan_token(req: &HttpRequest) -> String {
+    req.headers()
         .get("spartan_token")
         .map(|x| x.to_str())
         .transpose()
         .unwrap_or_default()
         .unwrap_or_default()
-        .to_string();
+        .to_string()
+}
 
+async fn index(
+    data: web::Data<ActixData>,
+    req: HttpRequest,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
     data.schema
-        .execute(request.into_inner().data(AuthData {
-            spartan_token: spartan_token.clone(),
-            client: data.client.clone(),
-            loader: DataLoader::new(
-                HaloLoader {
-                    client: data.client.clone(),
-                    spartan_token: spartan_token,
-                },
-                actix_web::rt::spawn,
-            ),
-        }))
+        .execute(
+            request
+                .into_inner()
+                .data(data.auth_data(spartan_token(&req))),
+        )
         .await
         .into()
 }
 
+async fn index_ws(
+    data: web::Data<ActixData>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    let header_token = spartan_token(&req);
+
+    GraphQLSubscription::new(data.schema.clone())
+        .on_connection_init(move |value| async move {
+            // Browsers can't set headers on websockets, so the token may come
+            // in the connection init payload instead
+            let spartan_token = value
+                .get("spartan_token")
+                .and_then(|x| x.as_str())
+                .map_or(header_token, str::to_string);
+
+            let mut init = async_graphql::Data::default();
+            init.insert(data.auth_data(spartan_token));
+
+            Ok(init)
+        })
+        .start(&req, payload)
+}
+
+/// Streams a theater film chunk from upstream storage.
+async fn film_chunk(
+    data: web::Data<ActixData>,
+    req: HttpRequest,
+    path: web::Path<(String, i32)>,
+) -> actix_web::Result<HttpResponse> {
+    let (match_id, index) = path.into_inner();
+    let spartan_token = spartan_token(&req);
+
+    let film = halo_requests::film(&data.client, &spartan_token, &match_id)
+        .await
+        .map_err(|err| actix_web::error::ErrorBadGateway(err.message))?
+        .ok_or(actix_web::error::ErrorNotFound("No film for this match"))?;
+    let chunk = film
+        .custom_data
+        .chunks
+        .iter()
+        .find(|x| x.index == index)
+        .ok_or(actix_web::error::ErrorNotFound("No such chunk"))?;
+
+    let res = halo_requests::film_chunk(&data.client, &spartan_token, &film, chunk)
+        .await
+        .map_err(actix_web::error::ErrorBadGateway)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .streaming(
+            res.bytes_stream()
+                .map(|x| x.map_err(actix_web::error::ErrorBadGateway)),
+        ))
+}
+
+/// Hit and miss counts of the shared caches in the Prometheus text format.
+async fn metrics(data: web::Data<ActixData>) -> HttpResponse {
+    let caches = [
+        (
+            "stats",
+            data.stats.hits(),
+            data.stats.misses(),
+            data.stats.len(),
+        ),
+        (
+            "skill",
+            data.skills.hits(),
+            data.skills.misses(),
+            data.skills.len(),
+        ),
+        (
+            "asset",
+            data.assets.hits(),
+            data.assets.misses(),
+            data.assets.len(),
+        ),
+        (
+            "item",
+            data.items.hits(),
+            data.items.misses(),
+            data.items.len(),
+        ),
+    ];
+
+    let mut body = String::new();
+
+    body.push_str("# TYPE cache_hits_total counter\n");
+    for (name, hits, _, _) in caches {
+        body.push_str(&format!("cache_hits_total{{cache=\"{name}\"}} {hits}\n"));
+    }
+    body.push_str("# TYPE cache_misses_total counter\n");
+    for (name, _, misses, _) in caches {
+        body.push_str(&format!(
+            "cache_misses_total{{cache=\"{name}\"}} {misses}\n"
+        ));
+    }
+    body.push_str("# TYPE cache_entries gauge\n");
+    for (name, _, _, len) in caches {
+        body.push_str(&format!("cache_entries{{cache=\"{name}\"}} {len}\n"));
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
+
+/// Entry limit of a shared cache, set with the `var` environment variable.
+fn cache_size(var: &str, default: usize) -> usize {
+    env::var(var)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Duration in seconds, such as the lifetime of shared cache entries, set
+/// with the `var` environment variable.
+fn env_seconds(var: &str, default: u64) -> std::time::Duration {
+    std::time::Duration::from_secs(
+        env::var(var)
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(default),
+    )
+}
+
 struct ActixData {
-    schema: Schema<Query, EmptyMutation, EmptySubscription>,
+    schema: Schema<Query, Mutation, Subscription>,
     client: Client,
+    assets: Arc<LruCache<AssetEntry, halo_requests::Asset>>,
+    items: Arc<LruCache<InventoryItemEntry, halo_requests::InventoryItem>>,
+    stats: Arc<LruCache<StatsEntry, halo_requests::MatchStats>>,
+    skills: Arc<LruCache<SkillEntry, halo_requests::Skill>>,
+    store: Option<Arc<Store>>,
+    medals: Arc<OnceCell<halo_requests::MedalMetadata>>,
+    career_ranks: Arc<OnceCell<halo_requests::CareerRankMetadata>>,
+    emblems: Arc<OnceCell<halo_requests::EmblemMapping>>,
+    seasons: Arc<TtlCache<(), Vec<Season>>>,
+    gamers: Arc<TtlCache<String, halo_requests::Gamer>>,
+    first_pages: Arc<halo_requests::FirstPageCache>,
+    watcher: Arc<MatchWatcher>,
+}
+
+impl ActixData {
+    fn auth_data(&self, spartan_token: String) -> AuthData {
+        AuthData {
+            spartan_token: spartan_token.clone(),
+            client: self.client.clone(),
+            loader: DataLoader::new(
+                HaloLoader {
+                    client: self.client.clone(),
+                    spartan_token,
+                    assets: self.assets.clone(),
+                    items: self.items.clone(),
+                    stats: self.stats.clone(),
+                    skills: self.skills.clone(),
+                    store: self.store.clone(),
+                },
+                actix_web::rt::spawn,
+            ),
+            medals: self.medals.clone(),
+            career_ranks: self.career_ranks.clone(),
+            emblems: self.emblems.clone(),
+            seasons: self.seasons.clone(),
+            gamers: self.gamers.clone(),
+            first_pages: self.first_pages.clone(),
+            store: self.store.clone(),
+            watcher: self.watcher.clone(),
+        }
+    }
 }
 
 pub struct AuthData {
     pub spartan_token: String,
     pub client: Client,
     pub loader: DataLoader<HaloLoader>,
+    pub medals: Arc<OnceCell<halo_requests::MedalMetadata>>,
+    pub career_ranks: Arc<OnceCell<halo_requests::CareerRankMetadata>>,
+    pub emblems: Arc<OnceCell<halo_requests::EmblemMapping>>,
+    /// Season calendar, refetched now and then to pick up new seasons
+    pub seasons: Arc<TtlCache<(), Vec<Season>>>,
+    /// Profiles keyed by the `gt(..)` or `xuid(..)` lookup used
+    pub gamers: Arc<TtlCache<String, halo_requests::Gamer>>,
+    pub first_pages: Arc<halo_requests::FirstPageCache>,
+    pub store: Option<Arc<Store>>,
+    pub watcher: Arc<MatchWatcher>,
+}
+
+impl AuthData {
+    fn store(&self) -> Result<&Store> {
+        self.store.as_deref().ok_or(async_graphql::Error::new(
+            "Archiving is disabled, set SQLITE_PATH",
+        ))
+    }
+
+    async fn stats(&self, match_id: &str) -> Result<halo_requests::MatchStats> {
+        self.loader
+            .load_one(StatsEntry {
+                match_id: match_id.to_string(),
+            })
+            .await?
+            .ok_or(async_graphql::Error::new("Failed to fetch"))
+    }
+
+    async fn gamer_by_xuid(&self, xuid: String) -> Result<halo_requests::Gamer> {
+        let client = self.client.clone();
+        let spartan_token = self.spartan_token.clone();
+
+        self.gamers
+            .get_or_fetch(format!("xuid({xuid})"), move || async move {
+                halo_requests::gamer_by_xuid(&client, &spartan_token, &xuid).await
+            })
+            .await
+    }
+
+    async fn medal_metadata(&self) -> Result<&halo_requests::MedalMetadata> {
+        self.medals
+            .get_or_try_init(|| halo_requests::medal_metadata(&self.client, &self.spartan_token))
+            .await
+    }
+
+    async fn career_rank_metadata(&self) -> Result<&halo_requests::CareerRankMetadata> {
+        self.career_ranks
+            .get_or_try_init(|| {
+                halo_requests::career_rank_metadata(&self.client, &self.spartan_token)
+            })
+            .await
+    }
+
+    async fn emblem_mapping(&self) -> Result<&halo_requests::EmblemMapping> {
+        self.emblems
+            .get_or_try_init(|| halo_requests::emblem_mapping(&self.client, &self.spartan_token))
+            .await
+    }
+
+    async fn seasons(&self) -> Result<Vec<Season>> {
+        let client = self.client.clone();
+        let spartan_token = self.spartan_token.clone();
+
+        self.seasons
+            .get_or_fetch((), move || async move {
+                let calendar = halo_requests::season_calendar(&client, &spartan_token).await?;
+
+                futures::future::try_join_all(calendar.seasons.into_iter().map(|x| {
+                    let client = &client;
+                    let spartan_token = &spartan_token;
+
+                    async move {
+                        let metadata = halo_requests::season_metadata(
+                            client,
+                            spartan_token,
+                            &x.season_metadata,
+                        )
+                        .await?;
+
+                        Ok::<_, async_graphql::Error>(Season {
+                            id: x.season_metadata,
+                            name: metadata.name.value,
+                            description: metadata.description.value,
+                            start_time: x.start_date.iso8601_date,
+                            end_time: x.end_date.iso8601_date,
+                            csr_season_path: x.csr_season_file_path,
+                            operation_track_path: x.operation_track_path,
+                        })
+                    }
+                }))
+                .await
+            })
+            .await
+    }
 }
 
 #[actix_web::main]
@@ -919,9 +2915,59 @@ async fn main() -> std::io::Result<()> {
 
     let client = Client::new();
 
+    let store = match env::var("SQLITE_PATH") {
+        Ok(path) => {
+            let key = env::var("CREDENTIAL_KEY")
+                .ok()
+                .map(|x| CredentialKey::new(&x))
+                .transpose()
+                .map_err(std::io::Error::other)?;
+
+            Some(Arc::new(
+                Store::open(&path, key).map_err(std::io::Error::other)?,
+            ))
+        }
+        Err(_) => None,
+    };
+
+    if let Some(store) = &store {
+        actix_web::rt::spawn(ingest::run(
+            client.clone(),
+            store.clone(),
+            env_seconds("INGEST_POLL_INTERVAL", 300),
+        ));
+    }
+
     let data = web::Data::new(ActixData {
-        schema: Schema::build(Query, EmptyMutation, EmptySubscription).finish(),
-        client: client,
+        schema: Schema::build(Query, Mutation, Subscription).finish(),
+        client: client.clone(),
+        assets: Arc::new(LruCache::new(cache_size("ASSET_CACHE_SIZE", 10000))),
+        items: Arc::new(LruCache::new(cache_size("ITEM_CACHE_SIZE", 10000))),
+        stats: Arc::new(LruCache::new(cache_size("STATS_CACHE_SIZE", 1000))),
+        skills: Arc::new(LruCache::new(cache_size("SKILL_CACHE_SIZE", 10000))),
+        store,
+        medals: Arc::new(OnceCell::new()),
+        career_ranks: Arc::new(OnceCell::new()),
+        emblems: Arc::new(OnceCell::new()),
+        seasons: Arc::new(TtlCache::new(
+            env_seconds("SEASONS_CACHE_TTL", 3600),
+            env_seconds("STALE_WHILE_REVALIDATE", 300),
+            1,
+        )),
+        gamers: Arc::new(TtlCache::new(
+            env_seconds("PROFILE_CACHE_TTL", 60),
+            env_seconds("STALE_WHILE_REVALIDATE", 300),
+            cache_size("PROFILE_CACHE_SIZE", 10000),
+        )),
+        first_pages: Arc::new(TtlCache::new(
+            env_seconds("MATCHES_CACHE_TTL", 15),
+            env_seconds("STALE_WHILE_REVALIDATE", 300),
+            cache_size("MATCHES_CACHE_SIZE", 10000),
+        )),
+        watcher: Arc::new(MatchWatcher::new(
+            client.clone(),
+            env_seconds("SUBSCRIPTION_POLL_INTERVAL", 10),
+        )),
     });
 
     HttpServer::new(move || {
@@ -929,6 +2975,13 @@ async fn main() -> std::io::Result<()> {
             .app_data(data.clone())
             .service(web::resource("/").guard(guard::Post()).to(index))
             .service(web::resource("/").guard(guard::Get()).to(index_graphiql))
+            .service(web::resource("/ws").guard(guard::Get()).to(index_ws))
+            .service(web::resource("/metrics").guard(guard::Get()).to(metrics))
+            .service(
+                web::resource("/films/{match_id}/chunks/{index}")
+                    .guard(guard::Get())
+                    .to(film_chunk),
+            )
     })
     .bind("127.0.0.1:8000")?
     .run()
@@ -0,0 +1,64 @@
+use async_graphql::ID;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+/// Globally unique id of a `Node`, encoded as URL safe base64 of the type
+/// name followed by the upstream ids.
+pub enum NodeId {
+    Player { xuid: String },
+    Match { match_id: String },
+    Team { match_id: String, team_id: i32 },
+}
+
+impl NodeId {
+    /// Match the object belongs to, for everything but players
+    pub fn match_id(&self) -> Option<&str> {
+        match self {
+            NodeId::Player { .. } => None,
+            NodeId::Match { match_id } | NodeId::Team { match_id, .. } => Some(match_id),
+        }
+    }
+}
+
+impl From<NodeId> for ID {
+    fn from(value: NodeId) -> Self {
+        let raw = match value {
+            NodeId::Player { xuid } => format!("Player:{xuid}"),
+            NodeId::Match { match_id } => format!("Match:{match_id}"),
+            NodeId::Team { match_id, team_id } => format!("Team:{match_id}:{team_id}"),
+        };
+
+        ID(URL_SAFE_NO_PAD.encode(raw))
+    }
+}
+
+impl TryFrom<&ID> for NodeId {
+    type Error = async_graphql::Error;
+
+    fn try_from(value: &ID) -> Result<Self, Self::Error> {
+        let invalid = || async_graphql::Error::new("Invalid node id");
+
+        let decoded = URL_SAFE_NO_PAD
+            .decode(value.as_str())
+            .map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (kind, rest) = decoded.split_once(':').ok_or_else(invalid)?;
+
+        match kind {
+            "Player" => Ok(NodeId::Player {
+                xuid: rest.to_string(),
+            }),
+            "Match" => Ok(NodeId::Match {
+                match_id: rest.to_string(),
+            }),
+            "Team" => {
+                let (match_id, team_id) = rest.rsplit_once(':').ok_or_else(invalid)?;
+
+                Ok(NodeId::Team {
+                    match_id: match_id.to_string(),
+                    team_id: team_id.parse().map_err(|_| invalid())?,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+/// ISO-8601 duration such as `PT12M3.5S`
+#[derive(Clone, PartialEq)]
+pub struct Duration {
+    iso: String,
+    seconds: f64,
+}
+
+impl Duration {
+    pub fn seconds(&self) -> f64 {
+        self.seconds
+    }
+}
+
+impl Default for Duration {
+    fn default() -> Self {
+        Duration {
+            iso: "PT0S".to_string(),
+            seconds: 0.0,
+        }
+    }
+}
+
+impl FromStr for Duration {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid ISO-8601 duration: {value}");
+
+        let (sign, rest) = match value.strip_prefix('-') {
+            Some(rest) => (-1.0, rest),
+            None => (1.0, value),
+        };
+        let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+        let (date, time) = match rest.split_once('T') {
+            Some((date, time)) if !time.is_empty() => (date, Some(time)),
+            Some(_) => return Err(invalid()),
+            None => (rest, None),
+        };
+
+        if date.is_empty() && time.is_none() {
+            return Err(invalid());
+        }
+
+        let mut seconds = 0.0;
+
+        for (part, units) in [(date, &[('W', 604800.0), ('D', 86400.0)][..])]
+            .into_iter()
+            .chain(time.map(|time| (time, &[('H', 3600.0), ('M', 60.0), ('S', 1.0)][..])))
+        {
+            let mut remaining = part;
+
+            for (unit, scale) in units {
+                if let Some((number, tail)) = remaining.split_once(*unit) {
+                    seconds += number.parse::<f64>().map_err(|_| invalid())? * scale;
+                    remaining = tail;
+                }
+            }
+
+            if !remaining.is_empty() {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Duration {
+            iso: value.to_string(),
+            seconds: sign * seconds,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Duration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Duration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.iso)
+    }
+}
+
+#[Scalar]
+impl ScalarType for Duration {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(iso) => iso.parse().map_err(InputValueError::custom),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.iso.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(iso: &str) -> f64 {
+        iso.parse::<Duration>().unwrap().seconds()
+    }
+
+    #[test]
+    fn parses_time_components() {
+        assert_eq!(seconds("PT12M3.5S"), 723.5);
+        assert_eq!(seconds("PT1H"), 3600.0);
+        assert_eq!(seconds("PT0S"), 0.0);
+    }
+
+    #[test]
+    fn parses_date_components() {
+        assert_eq!(seconds("P1W"), 604800.0);
+        assert_eq!(seconds("P2DT1H1M1S"), 2.0 * 86400.0 + 3661.0);
+    }
+
+    #[test]
+    fn parses_negative_durations() {
+        assert_eq!(seconds("-PT1.5S"), -1.5);
+    }
+
+    #[test]
+    fn keeps_the_original_text() {
+        let duration = "PT01M".parse::<Duration>().unwrap();
+
+        assert_eq!(serde_json::to_value(&duration).unwrap(), "PT01M");
+        assert_eq!(duration.seconds(), 60.0);
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for iso in [
+            "",
+            "P",
+            "PT",
+            "12S",
+            "PT1X",
+            "PTS",
+            "PT1S1M",
+            "P1M",
+            "PT1H garbage",
+        ] {
+            assert!(iso.parse::<Duration>().is_err(), "{iso} parsed");
+        }
+    }
+}
@@ -0,0 +1,855 @@
+use crate::halo_requests::{self, MatchStats, Skill};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS matches (
+    match_id TEXT PRIMARY KEY,
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    duration_seconds REAL NOT NULL,
+    lifecycle_mode INTEGER NOT NULL,
+    game_variant_category INTEGER NOT NULL,
+    playlist_id TEXT,
+    map_id TEXT NOT NULL,
+    map_version_id TEXT NOT NULL,
+    ugc_game_variant_id TEXT NOT NULL,
+    season_id TEXT,
+    stats_json TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS teams (
+    match_id TEXT NOT NULL REFERENCES matches (match_id) ON DELETE CASCADE,
+    team_id INTEGER NOT NULL,
+    outcome INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    score INTEGER NOT NULL,
+    PRIMARY KEY (match_id, team_id)
+);
+
+CREATE TABLE IF NOT EXISTS player_team_stats (
+    match_id TEXT NOT NULL REFERENCES matches (match_id) ON DELETE CASCADE,
+    player_id TEXT NOT NULL,
+    team_id INTEGER NOT NULL,
+    player_type INTEGER NOT NULL,
+    outcome INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    score INTEGER NOT NULL,
+    personal_score INTEGER NOT NULL,
+    kills INTEGER NOT NULL,
+    deaths INTEGER NOT NULL,
+    assists INTEGER NOT NULL,
+    kda REAL NOT NULL,
+    accuracy REAL NOT NULL,
+    shots_fired INTEGER NOT NULL,
+    shots_hit INTEGER NOT NULL,
+    damage_dealt INTEGER NOT NULL,
+    damage_taken INTEGER NOT NULL,
+    time_played_seconds REAL NOT NULL,
+    PRIMARY KEY (match_id, player_id, team_id)
+);
+
+CREATE INDEX IF NOT EXISTS player_team_stats_player ON player_team_stats (player_id);
+
+CREATE TABLE IF NOT EXISTS medals (
+    match_id TEXT NOT NULL REFERENCES matches (match_id) ON DELETE CASCADE,
+    player_id TEXT NOT NULL,
+    team_id INTEGER NOT NULL,
+    name_id INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    total_personal_score_awarded INTEGER NOT NULL,
+    PRIMARY KEY (match_id, player_id, team_id, name_id)
+);
+
+CREATE TABLE IF NOT EXISTS csr (
+    match_id TEXT NOT NULL,
+    player_id TEXT NOT NULL,
+    team_mmr REAL NOT NULL,
+    pre_match_csr INTEGER NOT NULL,
+    pre_match_tier TEXT NOT NULL,
+    pre_match_sub_tier INTEGER NOT NULL,
+    post_match_csr INTEGER NOT NULL,
+    post_match_tier TEXT NOT NULL,
+    post_match_sub_tier INTEGER NOT NULL,
+    skill_json TEXT NOT NULL,
+    PRIMARY KEY (match_id, player_id)
+);
+
+CREATE INDEX IF NOT EXISTS csr_player ON csr (player_id);
+
+CREATE TABLE IF NOT EXISTS enrollments (
+    xuid TEXT PRIMARY KEY,
+    gamertag TEXT NOT NULL,
+    enrolled_by TEXT NOT NULL,
+    sealed_refresh_token TEXT NOT NULL,
+    credential_expired INTEGER NOT NULL DEFAULT 0,
+    enrolled_at TEXT NOT NULL,
+    backfill_offset INTEGER NOT NULL DEFAULT 0,
+    backfill_complete INTEGER NOT NULL DEFAULT 0,
+    matches_ingested INTEGER NOT NULL DEFAULT 0,
+    newest_start_time TEXT,
+    last_sync TEXT,
+    last_error TEXT
+);
+";
+
+/// Player whose match history is archived by the ingestion worker.
+pub struct Enrollment {
+    pub xuid: String,
+    pub gamertag: String,
+    /// Xuid of whoever enrolled the player
+    pub enrolled_by: String,
+    /// OAuth refresh token to sign in as the player with, sealed with the
+    /// store's credential key
+    sealed_refresh_token: String,
+    /// Whether the refresh token was turned down, which stops syncing until
+    /// the player is enrolled again
+    pub credential_expired: bool,
+    pub enrolled_at: DateTime<Utc>,
+    /// Matches from the newest one the backfill has walked past
+    pub backfill_offset: usize,
+    pub backfill_complete: bool,
+    pub matches_ingested: usize,
+    /// Start time of the newest match seen, which polling stops at
+    pub newest_start_time: Option<DateTime<Utc>>,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Outcome of one sync round of an enrolled player.
+#[derive(Clone)]
+pub struct SyncProgress {
+    pub backfill_offset: usize,
+    pub backfill_complete: bool,
+    pub newest_start_time: Option<DateTime<Utc>>,
+    /// Matches archived during the round
+    pub ingested: usize,
+    pub error: Option<String>,
+}
+
+impl Enrollment {
+    /// Whether `xuid` may change the enrollment, which only the player and
+    /// whoever enrolled them can.
+    pub fn is_managed_by(&self, xuid: &str) -> bool {
+        self.xuid == xuid || self.enrolled_by == xuid
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Enrollment {
+            xuid: row.get("xuid")?,
+            gamertag: row.get("gamertag")?,
+            enrolled_by: row.get("enrolled_by")?,
+            sealed_refresh_token: row.get("sealed_refresh_token")?,
+            credential_expired: row.get("credential_expired")?,
+            enrolled_at: row.get("enrolled_at")?,
+            backfill_offset: row.get("backfill_offset")?,
+            backfill_complete: row.get("backfill_complete")?,
+            matches_ingested: row.get("matches_ingested")?,
+            newest_start_time: row.get("newest_start_time")?,
+            last_sync: row.get("last_sync")?,
+            last_error: row.get("last_error")?,
+        })
+    }
+}
+
+/// Key credentials kept in the store are sealed with, set as base64 with
+/// `CREDENTIAL_KEY`.
+pub struct CredentialKey(Aes256Gcm);
+
+/// Bytes of the random nonce sealed credentials start with.
+const NONCE_LEN: usize = 12;
+
+impl CredentialKey {
+    pub fn new(encoded: &str) -> Result<Self, String> {
+        let invalid = || "CREDENTIAL_KEY must be 32 bytes of base64".to_string();
+
+        let bytes = STANDARD.decode(encoded.trim()).map_err(|_| invalid())?;
+
+        Aes256Gcm::new_from_slice(&bytes)
+            .map(CredentialKey)
+            .map_err(|_| invalid())
+    }
+
+    fn seal(&self, plaintext: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.0
+                .encrypt(&nonce, plaintext.as_bytes())
+                .expect("Encrypting in memory can't fail"),
+        );
+
+        STANDARD.encode(sealed)
+    }
+
+    fn open(&self, sealed: &str) -> async_graphql::Result<String> {
+        let invalid = || async_graphql::Error::new("Stored credential can't be opened");
+
+        let bytes = STANDARD.decode(sealed).map_err(|_| invalid())?;
+
+        if bytes.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| invalid())?;
+
+        String::from_utf8(plaintext).map_err(|_| invalid())
+    }
+}
+
+/// Local SQLite archive of fetched match stats and skill results, so they can
+/// be served and analysed without going back to the upstream.
+///
+/// Besides the normalised tables the raw upstream responses are kept as JSON
+/// to rebuild them from. Queries run on the blocking thread pool, off the
+/// async workers.
+///
+/// Enrollment credentials are only kept sealed with `key`, without which
+/// enrolling is disabled.
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+    key: Option<CredentialKey>,
+}
+
+impl Store {
+    pub fn open(path: &str, key: Option<CredentialKey>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Store {
+            conn: Arc::new(Mutex::new(conn)),
+            key,
+        })
+    }
+
+    fn key(&self) -> async_graphql::Result<&CredentialKey> {
+        self.key.as_ref().ok_or(async_graphql::Error::new(
+            "Enrolling is disabled, set CREDENTIAL_KEY",
+        ))
+    }
+
+    async fn call<T, F>(&self, f: F) -> async_graphql::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+
+        Ok(actix_web::web::block(move || f(&mut conn.lock().unwrap())).await??)
+    }
+
+    pub async fn stats(&self, match_id: &str) -> async_graphql::Result<Option<MatchStats>> {
+        let match_id = match_id.to_string();
+
+        let json: Option<String> = self
+            .call(move |conn| {
+                conn.query_row(
+                    "SELECT stats_json FROM matches WHERE match_id = ?1",
+                    params![match_id],
+                    |row| row.get(0),
+                )
+                .optional()
+            })
+            .await?;
+
+        json.map(|x| halo_requests::decode_value(&serde_json::from_str(&x)?))
+            .transpose()
+    }
+
+    /// Archives match stats, along with the raw JSON they were decoded from.
+    pub async fn save_stats(&self, stats: &MatchStats, raw: &Value) -> async_graphql::Result<()> {
+        let stats = stats.clone();
+        let json = raw.to_string();
+
+        self.call(move |conn| {
+            let info = &stats.match_info;
+            let tx = conn.transaction()?;
+
+            // Cascades to the rows of any previous copy
+            tx.execute(
+                "DELETE FROM matches WHERE match_id = ?1",
+                params![stats.match_id],
+            )?;
+            tx.execute(
+                "INSERT INTO matches VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    stats.match_id,
+                    info.start_time.to_rfc3339(),
+                    info.end_time.to_rfc3339(),
+                    info.duration.seconds(),
+                    info.lifecycle_mode,
+                    info.game_variant_category,
+                    info.playlist.as_ref().map(|x| &x.asset_id),
+                    info.map_variant.asset_id,
+                    info.map_variant.version_id,
+                    info.ugc_game_variant.asset_id,
+                    info.season_id.as_ref().and_then(|x| x.as_str()),
+                    json,
+                ],
+            )?;
+
+            for team in &stats.teams {
+                tx.execute(
+                    "INSERT INTO teams VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        stats.match_id,
+                        team.team_id,
+                        team.outcome,
+                        team.rank,
+                        team.stats.core_stats.score,
+                    ],
+                )?;
+            }
+
+            for player in &stats.players {
+                for team in &player.player_team_stats {
+                    let core = &team.stats.core_stats;
+
+                    tx.execute(
+                        "INSERT INTO player_team_stats VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                        params![
+                            stats.match_id,
+                            player.player_id,
+                            team.team_id,
+                            player.player_type,
+                            player.outcome,
+                            player.rank,
+                            core.score,
+                            core.personal_score,
+                            core.kills,
+                            core.deaths,
+                            core.assists,
+                            core.kda,
+                            core.accuracy,
+                            core.shots_fired,
+                            core.shots_hit,
+                            core.damage_dealt,
+                            core.damage_taken,
+                            player.participation_info.time_played.seconds(),
+                        ],
+                    )?;
+
+                    for medal in &core.medals {
+                        tx.execute(
+                            "INSERT INTO medals VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                            params![
+                                stats.match_id,
+                                player.player_id,
+                                team.team_id,
+                                medal.name_id,
+                                medal.count,
+                                medal.total_personal_score_awarded,
+                            ],
+                        )?;
+                    }
+                }
+            }
+
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Archived skill results of the given `(match_id, player_id)` pairs,
+    /// read in one go.
+    pub async fn skills(
+        &self,
+        keys: &[(String, String)],
+    ) -> async_graphql::Result<HashMap<(String, String), Skill>> {
+        let keys = keys.to_vec();
+
+        let rows: Vec<((String, String), String)> = self
+            .call(move |conn| {
+                let mut statement = conn.prepare_cached(
+                    "SELECT skill_json FROM csr WHERE match_id = ?1 AND player_id = ?2",
+                )?;
+                let mut rows = Vec::new();
+
+                for (match_id, player_id) in keys {
+                    let json: Option<String> = statement
+                        .query_row(params![match_id, player_id], |row| row.get(0))
+                        .optional()?;
+
+                    if let Some(json) = json {
+                        rows.push(((match_id, player_id), json));
+                    }
+                }
+
+                Ok(rows)
+            })
+            .await?;
+
+        rows.into_iter()
+            .map(|(key, json)| {
+                Ok((
+                    key,
+                    halo_requests::decode_value(&serde_json::from_str(&json)?)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Archives a player's skill result, along with the raw JSON it was
+    /// decoded from.
+    pub async fn save_skill(
+        &self,
+        match_id: &str,
+        player_id: &str,
+        skill: &Skill,
+        raw: &Value,
+    ) -> async_graphql::Result<()> {
+        let match_id = match_id.to_string();
+        let player_id = player_id.to_string();
+        let team_mmr = skill.result.team_mmr;
+        let pre = skill.result.rank_recap.pre_match_csr.clone();
+        let post = skill.result.rank_recap.post_match_csr.clone();
+        let json = raw.to_string();
+
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO csr VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    match_id,
+                    player_id,
+                    team_mmr,
+                    pre.value,
+                    pre.tier,
+                    pre.sub_tier,
+                    post.value,
+                    post.tier,
+                    post.sub_tier,
+                    json,
+                ],
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn has_match(&self, match_id: &str) -> async_graphql::Result<bool> {
+        let match_id = match_id.to_string();
+
+        self.call(move |conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM matches WHERE match_id = ?1)",
+                params![match_id],
+                |row| row.get(0),
+            )
+        })
+        .await
+    }
+
+    /// Enrolls a player, or just replaces the refresh token of an enrolled
+    /// one.
+    pub async fn enroll(
+        &self,
+        xuid: &str,
+        gamertag: &str,
+        enrolled_by: &str,
+        refresh_token: &str,
+    ) -> async_graphql::Result<Enrollment> {
+        let xuid = xuid.to_string();
+        let gamertag = gamertag.to_string();
+        let enrolled_by = enrolled_by.to_string();
+        let sealed = self.key()?.seal(refresh_token);
+
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT INTO enrollments (xuid, gamertag, enrolled_by, sealed_refresh_token, enrolled_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (xuid) DO UPDATE SET
+                    gamertag = excluded.gamertag,
+                    enrolled_by = excluded.enrolled_by,
+                    sealed_refresh_token = excluded.sealed_refresh_token,
+                    credential_expired = 0,
+                    last_error = NULL",
+                params![xuid, gamertag, enrolled_by, sealed, Utc::now()],
+            )?;
+
+            conn.query_row(
+                "SELECT * FROM enrollments WHERE xuid = ?1",
+                params![xuid],
+                Enrollment::from_row,
+            )
+        })
+        .await
+    }
+
+    /// Refresh token to sign in as an enrolled player with.
+    pub fn refresh_token(&self, enrollment: &Enrollment) -> async_graphql::Result<String> {
+        self.key()?.open(&enrollment.sealed_refresh_token)
+    }
+
+    /// Replaces the refresh token of an enrolled player after signing in
+    /// with it gave out a new one.
+    pub async fn renew_refresh_token(
+        &self,
+        xuid: &str,
+        refresh_token: &str,
+    ) -> async_graphql::Result<()> {
+        let xuid = xuid.to_string();
+        let sealed = self.key()?.seal(refresh_token);
+
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE enrollments SET sealed_refresh_token = ?2 WHERE xuid = ?1",
+                params![xuid, sealed],
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stops syncing a player whose refresh token was turned down, until they
+    /// are enrolled again.
+    pub async fn expire_credential(&self, xuid: &str) -> async_graphql::Result<()> {
+        let xuid = xuid.to_string();
+
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE enrollments SET credential_expired = 1 WHERE xuid = ?1",
+                params![xuid],
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unenroll(&self, xuid: &str) -> async_graphql::Result<bool> {
+        let xuid = xuid.to_string();
+
+        self.call(move |conn| {
+            Ok(conn.execute("DELETE FROM enrollments WHERE xuid = ?1", params![xuid])? > 0)
+        })
+        .await
+    }
+
+    pub async fn enrollment(&self, xuid: &str) -> async_graphql::Result<Option<Enrollment>> {
+        let xuid = xuid.to_string();
+
+        self.call(move |conn| {
+            conn.query_row(
+                "SELECT * FROM enrollments WHERE xuid = ?1",
+                params![xuid],
+                Enrollment::from_row,
+            )
+            .optional()
+        })
+        .await
+    }
+
+    pub async fn enrollments(&self) -> async_graphql::Result<Vec<Enrollment>> {
+        self.call(|conn| {
+            let mut statement = conn.prepare("SELECT * FROM enrollments ORDER BY enrolled_at")?;
+            let rows = statement.query_map([], Enrollment::from_row)?;
+
+            rows.collect()
+        })
+        .await
+    }
+
+    /// Records the outcome of a sync round for an enrolled player.
+    pub async fn record_sync(
+        &self,
+        xuid: &str,
+        progress: &SyncProgress,
+    ) -> async_graphql::Result<()> {
+        let xuid = xuid.to_string();
+        let progress = progress.clone();
+
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE enrollments SET
+                    backfill_offset = ?2,
+                    backfill_complete = ?3,
+                    newest_start_time = ?4,
+                    matches_ingested = matches_ingested + ?5,
+                    last_sync = ?6,
+                    last_error = ?7
+                WHERE xuid = ?1",
+                params![
+                    xuid,
+                    progress.backfill_offset,
+                    progress.backfill_complete,
+                    progress.newest_start_time,
+                    progress.ingested,
+                    Utc::now(),
+                    progress.error
+                ],
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::halo_requests::tests::test_match;
+    use serde_json::json;
+
+    fn store() -> Store {
+        Store::open(":memory:", None).unwrap()
+    }
+
+    fn raw_stats(match_id: &str) -> Value {
+        let info = serde_json::to_value(test_match(match_id, Utc::now()).match_info).unwrap();
+        let core_stats = json!({
+            "Score": 50,
+            "PersonalScore": 1200,
+            "Kills": 10,
+            "Deaths": 5,
+            "KDA": 7.5,
+            "Medals": [{ "NameId": 1512363953, "Count": 2, "TotalPersonalScoreAwarded": 100 }],
+            "Unmodelled": true,
+        });
+
+        json!({
+            "MatchId": match_id,
+            "MatchInfo": info,
+            "Teams": [{ "TeamId": 0, "Outcome": 2, "Rank": 1, "Stats": { "CoreStats": core_stats } }],
+            "Players": [{
+                "PlayerId": "xuid(1)",
+                "PlayerType": 1,
+                "LastTeamId": 0,
+                "Outcome": 2,
+                "Rank": 1,
+                "ParticipationInfo": { "TimePlayed": "PT9M30S" },
+                "PlayerTeamStats": [{ "TeamId": 0, "Stats": { "CoreStats": core_stats } }],
+            }],
+        })
+    }
+
+    fn raw_skill() -> Value {
+        json!({
+            "Id": "xuid(1)",
+            "ResultCode": 0,
+            "Result": {
+                "TeamMmr": 1450.5,
+                "RankRecap": {
+                    "PreMatchCsr": { "Value": 1400, "Tier": "Diamond", "SubTier": 3 },
+                    "PostMatchCsr": { "Value": 1412, "Tier": "Diamond", "SubTier": 3 },
+                },
+                "Unmodelled": true,
+            },
+        })
+    }
+
+    #[actix_web::test]
+    async fn round_trips_match_stats() {
+        let store = store();
+        let raw = raw_stats("m1");
+        let stats: MatchStats = halo_requests::decode_value(&raw).unwrap();
+
+        assert!(!store.has_match("m1").await.unwrap());
+        assert!(store.stats("m1").await.unwrap().is_none());
+
+        store.save_stats(&stats, &raw).await.unwrap();
+
+        assert!(store.has_match("m1").await.unwrap());
+
+        let stored = store.stats("m1").await.unwrap().unwrap();
+        assert_eq!(stored.match_id, "m1");
+        assert_eq!(stored.players[0].player_id, "xuid(1)");
+
+        let core = &stored.players[0].player_team_stats[0].stats.core_stats;
+        assert_eq!(core.kills, 10);
+        assert_eq!(core.medals[0].count, 2);
+        // Fields the types leave out come back too, from the raw JSON
+        assert_eq!(core.extra.get("Unmodelled"), Some(&Value::Bool(true)));
+    }
+
+    #[actix_web::test]
+    async fn replaces_previous_copies_of_a_match() {
+        let store = store();
+        let raw = raw_stats("m1");
+        let stats: MatchStats = halo_requests::decode_value(&raw).unwrap();
+
+        store.save_stats(&stats, &raw).await.unwrap();
+        store.save_stats(&stats, &raw).await.unwrap();
+
+        let rows: i64 = store
+            .call(|conn| {
+                conn.query_row(
+                    "SELECT (SELECT COUNT(*) FROM teams) + (SELECT COUNT(*) FROM player_team_stats) + (SELECT COUNT(*) FROM medals)",
+                    [],
+                    |row| row.get(0),
+                )
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(rows, 3);
+    }
+
+    #[actix_web::test]
+    async fn round_trips_skill_results() {
+        let store = store();
+        let raw = raw_skill();
+        let skill: Skill = halo_requests::decode_value(&raw).unwrap();
+
+        let key = |player_id: &str| ("m1".to_string(), player_id.to_string());
+
+        assert!(store.skills(&[key("xuid(1)")]).await.unwrap().is_empty());
+
+        store
+            .save_skill("m1", "xuid(1)", &skill, &raw)
+            .await
+            .unwrap();
+
+        let stored = store
+            .skills(&[key("xuid(1)"), key("xuid(2)")])
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+
+        let stored = &stored[&key("xuid(1)")];
+        assert_eq!(stored.result.rank_recap.post_match_csr.value, 1412);
+        assert_eq!(
+            stored.result.extra.get("Unmodelled"),
+            Some(&Value::Bool(true))
+        );
+    }
+
+    fn key() -> CredentialKey {
+        CredentialKey::new(&STANDARD.encode([7; 32])).unwrap()
+    }
+
+    fn progress(error: Option<&str>) -> SyncProgress {
+        SyncProgress {
+            backfill_offset: 100,
+            backfill_complete: false,
+            newest_start_time: Some(Utc::now()),
+            ingested: 4,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn seals_and_opens_credentials() {
+        let key = key();
+        let sealed = key.seal("refresh");
+
+        assert!(!sealed.contains("refresh"));
+        // Fresh nonce each time
+        assert_ne!(sealed, key.seal("refresh"));
+        assert_eq!(key.open(&sealed).unwrap(), "refresh");
+    }
+
+    #[test]
+    fn rejects_tampered_or_foreign_credentials() {
+        let sealed = key().seal("refresh");
+        let mut bytes = STANDARD.decode(&sealed).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+
+        let other = CredentialKey::new(&STANDARD.encode([8; 32])).unwrap();
+
+        assert!(key().open(&STANDARD.encode(bytes)).is_err());
+        assert!(key().open("short").is_err());
+        assert!(key().open("not base64!").is_err());
+        assert!(other.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(CredentialKey::new("not base64!").is_err());
+        assert!(CredentialKey::new(&STANDARD.encode([7; 16])).is_err());
+        assert!(CredentialKey::new(&format!(" {}\n", STANDARD.encode([7; 32]))).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn enrolling_needs_a_key() {
+        assert!(store()
+            .enroll("xuid(1)", "One", "xuid(1)", "refresh")
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn round_trips_enrollments() {
+        let store = Store::open(":memory:", Some(key())).unwrap();
+
+        let enrollment = store
+            .enroll("xuid(1)", "One", "xuid(2)", "refresh")
+            .await
+            .unwrap();
+        assert_eq!(enrollment.enrolled_by, "xuid(2)");
+        assert!(enrollment.is_managed_by("xuid(1)"));
+        assert!(enrollment.is_managed_by("xuid(2)"));
+        assert!(!enrollment.is_managed_by("xuid(3)"));
+        assert_eq!(store.refresh_token(&enrollment).unwrap(), "refresh");
+
+        store
+            .renew_refresh_token("xuid(1)", "renewed")
+            .await
+            .unwrap();
+        store.record_sync("xuid(1)", &progress(None)).await.unwrap();
+        store
+            .record_sync("xuid(1)", &progress(Some("down")))
+            .await
+            .unwrap();
+
+        let enrollment = store.enrollment("xuid(1)").await.unwrap().unwrap();
+        assert_eq!(store.refresh_token(&enrollment).unwrap(), "renewed");
+        assert_eq!(enrollment.backfill_offset, 100);
+        assert_eq!(enrollment.matches_ingested, 8);
+        assert_eq!(enrollment.last_error.as_deref(), Some("down"));
+        assert!(enrollment.last_sync.is_some());
+
+        assert_eq!(store.enrollments().await.unwrap().len(), 1);
+        assert!(store.unenroll("xuid(1)").await.unwrap());
+        assert!(!store.unenroll("xuid(1)").await.unwrap());
+        assert!(store.enrollment("xuid(1)").await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn reenrolling_clears_an_expired_credential() {
+        let store = Store::open(":memory:", Some(key())).unwrap();
+
+        store
+            .enroll("xuid(1)", "One", "xuid(1)", "refresh")
+            .await
+            .unwrap();
+        store
+            .record_sync("xuid(1)", &progress(Some("down")))
+            .await
+            .unwrap();
+        store.expire_credential("xuid(1)").await.unwrap();
+
+        assert!(
+            store
+                .enrollment("xuid(1)")
+                .await
+                .unwrap()
+                .unwrap()
+                .credential_expired
+        );
+
+        let enrollment = store
+            .enroll("xuid(1)", "One", "xuid(1)", "again")
+            .await
+            .unwrap();
+        assert!(!enrollment.credential_expired);
+        assert!(enrollment.last_error.is_none());
+        // Progress survives
+        assert_eq!(enrollment.matches_ingested, 4);
+        assert_eq!(store.refresh_token(&enrollment).unwrap(), "again");
+    }
+}
@@ -0,0 +1,340 @@
+use crate::halo_requests;
+use async_graphql::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// New matches buffered per player for subscribers that fall behind.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Polls the match history of players with live subscriptions, one poller per
+/// player no matter how many subscribers it has, so each new match is fetched
+/// and emitted once.
+pub struct MatchWatcher {
+    client: Client,
+    interval: Duration,
+    watches: Mutex<HashMap<String, Watch>>,
+    next_id: AtomicU64,
+}
+
+struct Watch {
+    /// New matches, or the error that ended the watch
+    sender: broadcast::Sender<Result<halo_requests::Match>>,
+    /// Token of each subscriber by subscription id, polled with oldest first
+    /// until one is turned down
+    spartan_tokens: BTreeMap<u64, String>,
+}
+
+/// Subscription to the new matches of a player, which gives up its token
+/// when dropped.
+pub struct MatchReceiver {
+    receiver: broadcast::Receiver<Result<halo_requests::Match>>,
+    watcher: Arc<MatchWatcher>,
+    xuid: String,
+    id: u64,
+}
+
+impl MatchReceiver {
+    /// Next new match, or `None` once the watch is over.
+    pub async fn recv(&mut self) -> Option<Result<halo_requests::Match>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(x) => return Some(x),
+                // Matches missed by a slow subscriber are gone, carry on
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for MatchReceiver {
+    fn drop(&mut self) {
+        self.watcher.remove_token(&self.xuid, self.id);
+    }
+}
+
+impl MatchWatcher {
+    pub fn new(client: Client, interval: Duration) -> Self {
+        MatchWatcher {
+            client,
+            interval,
+            watches: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Receives matches the player completes from now on, starting a poller
+    /// for them if there isn't one yet.
+    pub async fn subscribe(
+        self: &Arc<Self>,
+        xuid: &str,
+        spartan_token: &str,
+    ) -> Result<MatchReceiver> {
+        if let Some(receiver) = self.join(xuid, spartan_token) {
+            return Ok(receiver);
+        }
+
+        // Matches up to the newest one at subscription time are not new
+        let res = self.newest_page(xuid, spartan_token).await?;
+        let watermark = newest_start_time(&res.results).unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+        let mut watches = self.watches.lock().unwrap();
+
+        // Someone else may have started a poller in the meantime
+        if !watches.contains_key(xuid) {
+            let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+            watches.insert(
+                xuid.to_string(),
+                Watch {
+                    sender,
+                    spartan_tokens: BTreeMap::new(),
+                },
+            );
+            actix_web::rt::spawn(self.clone().poll(xuid.to_string(), watermark));
+        }
+
+        Ok(self.add_subscriber(&mut watches, xuid, spartan_token))
+    }
+
+    fn join(self: &Arc<Self>, xuid: &str, spartan_token: &str) -> Option<MatchReceiver> {
+        let mut watches = self.watches.lock().unwrap();
+
+        watches
+            .contains_key(xuid)
+            .then(|| self.add_subscriber(&mut watches, xuid, spartan_token))
+    }
+
+    fn add_subscriber(
+        self: &Arc<Self>,
+        watches: &mut HashMap<String, Watch>,
+        xuid: &str,
+        spartan_token: &str,
+    ) -> MatchReceiver {
+        let watch = watches.get_mut(xuid).unwrap();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        watch.spartan_tokens.insert(id, spartan_token.to_string());
+
+        MatchReceiver {
+            receiver: watch.sender.subscribe(),
+            watcher: self.clone(),
+            xuid: xuid.to_string(),
+            id,
+        }
+    }
+
+    async fn poll(self: Arc<Self>, xuid: String, mut watermark: DateTime<Utc>) {
+        loop {
+            actix_web::rt::time::sleep(self.interval).await;
+
+            let res = loop {
+                let Some((id, spartan_token)) = self.spartan_token(&xuid) else {
+                    return;
+                };
+
+                match self.newest_page(&xuid, &spartan_token).await {
+                    Err(err) if halo_requests::is_unauthorized(&err) => {
+                        self.remove_token(&xuid, id);
+                    }
+                    res => break res,
+                }
+            };
+
+            match res {
+                Ok(res) => {
+                    for x in new_matches(res.results, &mut watermark) {
+                        self.send(&xuid, Ok(x));
+                    }
+                }
+                Err(err) => println!("Failed to poll matches of {xuid}: {}", err.message),
+            }
+        }
+    }
+
+    async fn newest_page(
+        &self,
+        xuid: &str,
+        spartan_token: &str,
+    ) -> Result<halo_requests::MatchesResponse> {
+        halo_requests::matches(
+            &self.client,
+            spartan_token,
+            xuid,
+            None,
+            Some(0),
+            Some(halo_requests::MATCHES_PAGE_SIZE),
+        )
+        .await
+    }
+
+    /// Token to poll with along with its subscription id, or `None` once
+    /// there is nothing left to poll with, in which case the watch is dropped.
+    fn spartan_token(&self, xuid: &str) -> Option<(u64, String)> {
+        let mut watches = self.watches.lock().unwrap();
+        let watch = watches.get(xuid)?;
+
+        if let Some((id, spartan_token)) = watch.spartan_tokens.first_key_value() {
+            return Some((*id, spartan_token.clone()));
+        }
+
+        // Subscribers still listening had their tokens turned down
+        if watch.sender.receiver_count() > 0 {
+            let _ = watch.sender.send(Err(async_graphql::Error::new(
+                "Every token watching this player was turned down",
+            )));
+        }
+
+        // Dropping the sender ends the streams
+        watches.remove(xuid);
+
+        None
+    }
+
+    fn remove_token(&self, xuid: &str, id: u64) {
+        if let Some(watch) = self.watches.lock().unwrap().get_mut(xuid) {
+            watch.spartan_tokens.remove(&id);
+        }
+    }
+
+    fn send(&self, xuid: &str, x: Result<halo_requests::Match>) {
+        if let Some(watch) = self.watches.lock().unwrap().get(xuid) {
+            // Only fails when everyone unsubscribed, which the next round
+            // notices
+            let _ = watch.sender.send(x);
+        }
+    }
+}
+
+/// Matches of a history page that started after `watermark`, oldest first so
+/// subscribers see them in order, moving the watermark past them.
+fn new_matches(
+    page: Vec<halo_requests::Match>,
+    watermark: &mut DateTime<Utc>,
+) -> Vec<halo_requests::Match> {
+    let previous = *watermark;
+    *watermark = previous.max(newest_start_time(&page).unwrap_or(previous));
+
+    page.into_iter()
+        .rev()
+        .filter(|x| x.match_info.start_time > previous)
+        .collect()
+}
+
+fn newest_start_time(matches: &[halo_requests::Match]) -> Option<DateTime<Utc>> {
+    matches.iter().map(|x| x.match_info.start_time).max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::halo_requests::tests::{at, page, test_match};
+
+    fn ids(matches: &[halo_requests::Match]) -> Vec<&str> {
+        matches.iter().map(|x| x.match_id.as_str()).collect()
+    }
+
+    fn watcher() -> Arc<MatchWatcher> {
+        Arc::new(MatchWatcher::new(Client::new(), Duration::from_secs(60)))
+    }
+
+    /// Starts watching a player without polling.
+    fn watch(watcher: &Arc<MatchWatcher>, xuid: &str) {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+        watcher.watches.lock().unwrap().insert(
+            xuid.to_string(),
+            Watch {
+                sender,
+                spartan_tokens: BTreeMap::new(),
+            },
+        );
+    }
+
+    #[test]
+    fn emits_each_new_match_once() {
+        let mut watermark = at(10);
+
+        let first = new_matches(page(&[12, 11, 10, 9]), &mut watermark);
+        assert_eq!(ids(&first), ["m11", "m12"]);
+        assert_eq!(watermark, at(12));
+
+        let second = new_matches(page(&[13, 12, 11, 10]), &mut watermark);
+        assert_eq!(ids(&second), ["m13"]);
+        assert_eq!(watermark, at(13));
+
+        assert!(new_matches(page(&[13, 12]), &mut watermark).is_empty());
+        assert!(new_matches(Vec::new(), &mut watermark).is_empty());
+        assert_eq!(watermark, at(13));
+    }
+
+    #[test]
+    fn emits_everything_for_an_empty_history() {
+        let mut watermark = DateTime::<Utc>::MIN_UTC;
+
+        assert_eq!(
+            ids(&new_matches(page(&[2, 1]), &mut watermark)),
+            ["m1", "m2"]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_other_subscribers_tokens() {
+        let watcher = watcher();
+        watch(&watcher, "x");
+
+        let first = watcher.join("x", "a").unwrap();
+        let second = watcher.join("x", "b").unwrap();
+
+        assert_eq!(
+            watcher.spartan_token("x"),
+            Some((first.id, "a".to_string()))
+        );
+
+        // Turned down
+        watcher.remove_token("x", first.id);
+        assert_eq!(
+            watcher.spartan_token("x"),
+            Some((second.id, "b".to_string()))
+        );
+
+        // Unsubscribed
+        drop(second);
+        assert_eq!(watcher.spartan_token("x"), None);
+        assert!(watcher.join("x", "c").is_none());
+    }
+
+    #[actix_web::test]
+    async fn ends_streams_with_an_error_once_every_token_is_turned_down() {
+        let watcher = watcher();
+        watch(&watcher, "x");
+
+        let mut receiver = watcher.join("x", "a").unwrap();
+        watcher.send("x", Ok(test_match("m1", at(1))));
+        watcher.remove_token("x", receiver.id);
+
+        assert_eq!(watcher.spartan_token("x"), None);
+        assert_eq!(receiver.recv().await.unwrap().unwrap().match_id, "m1");
+        assert!(receiver.recv().await.unwrap().is_err());
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[actix_web::test]
+    async fn skips_matches_missed_by_slow_subscribers() {
+        let watcher = watcher();
+        watch(&watcher, "x");
+
+        let mut receiver = watcher.join("x", "a").unwrap();
+
+        for ind in 0..CHANNEL_CAPACITY + 2 {
+            watcher.send("x", Ok(test_match(&format!("m{ind}"), at(ind as i64))));
+        }
+
+        assert_eq!(receiver.recv().await.unwrap().unwrap().match_id, "m2");
+    }
+}